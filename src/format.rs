@@ -0,0 +1,322 @@
+//! Parsing and rendering of log message format strings
+//!
+//! A format string is literal text mixed with `{token}`s, which are
+//! replaced by fields of the record being logged, and conditional
+//! sections `{?token}...{/token}`, whose contents are only rendered when
+//! the named field is available. A token may carry a modifier changing how
+//! its value is rendered, e.g. `{msg:json}`.
+
+/// Source of token values when rendering a template
+pub(crate) trait Fields {
+	/// Returns the rendered value of a token, `None` if the token is unknown
+	fn value(&self, name: &str) -> Option<String>;
+
+	/// Returns whether the field behind a token is available
+	fn present(&self, name: &str) -> bool {
+		self.value(name).is_some_and(|value| !value.is_empty())
+	}
+}
+
+/// Transformation applied to a token's value
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Modifier {
+	/// Escapes the value for use inside a JSON string
+	Json,
+}
+
+impl Modifier {
+	fn parse(name: &str) -> Option<Modifier> {
+		match name {
+			"json" => Some(Modifier::Json),
+			_ => None,
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			Modifier::Json => "json",
+		}
+	}
+
+	fn apply(self, value: &str, out: &mut String) {
+		match self {
+			Modifier::Json => escape_json(value, out),
+		}
+	}
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+	Literal(String),
+	Token(String, Option<Modifier>),
+	Conditional(String, Vec<Segment>),
+}
+
+/// A parsed format string
+#[derive(Debug, PartialEq)]
+pub(crate) struct Template {
+	segments: Vec<Segment>,
+}
+
+impl Template {
+	/// Parses a format string. Braces that don't form a token, and
+	/// conditional sections that are never closed, are kept as literal text
+	pub(crate) fn parse(fmt: &str) -> Template {
+		// Sections still waiting for their closing tag, innermost last
+		let mut open: Vec<(String, Vec<Segment>)> = Vec::new();
+		let mut segments = Vec::new();
+		let mut rest = fmt;
+		while let Some(start) = rest.find('{') {
+			push_literal(&mut segments, &rest[..start]);
+			let tag = &rest[start..];
+			let end = match tag[1..].find(['{', '}']) {
+				Some(i) if tag.as_bytes()[i + 1] == b'}' => i + 1,
+				_ => {
+					push_literal(&mut segments, "{");
+					rest = &tag[1..];
+					continue;
+				}
+			};
+			let name = &tag[1..end];
+			if let Some(name) = name.strip_prefix('?') {
+				open.push((name.to_owned(), segments));
+				segments = Vec::new();
+			} else if let Some(name) = name.strip_prefix('/') {
+				match open.last() {
+					Some((open_name, _)) if open_name == name => {
+						let (name, mut parent) = open.pop().unwrap();
+						parent.push(Segment::Conditional(name, segments));
+						segments = parent;
+					}
+					_ => push_literal(&mut segments, &tag[..=end]),
+				}
+			} else {
+				segments.push(match name.rsplit_once(':') {
+					Some((name, modifier)) if Modifier::parse(modifier).is_some() => {
+						Segment::Token(name.to_owned(), Modifier::parse(modifier))
+					}
+					_ => Segment::Token(name.to_owned(), None),
+				});
+			}
+			rest = &tag[end + 1..];
+		}
+		push_literal(&mut segments, rest);
+		while let Some((name, mut parent)) = open.pop() {
+			push_literal(&mut parent, &format!("{{?{}}}", name));
+			parent.extend(segments);
+			segments = parent;
+		}
+		Template { segments }
+	}
+
+	/// Returns whether the template renders the token `name` anywhere,
+	/// including inside conditional sections
+	pub(crate) fn uses(&self, name: &str) -> bool {
+		fn uses(segments: &[Segment], name: &str) -> bool {
+			segments.iter().any(|segment| match segment {
+				Segment::Literal(_) => false,
+				Segment::Token(token, _) => token == name,
+				Segment::Conditional(_, body) => uses(body, name),
+			})
+		}
+		uses(&self.segments, name)
+	}
+
+	/// Renders the template, leaving unknown tokens as they were written
+	pub(crate) fn render(&self, fields: &dyn Fields) -> String {
+		self.render_with(fields, false)
+	}
+
+	/// Renders the template, dropping the spaces around tokens and
+	/// conditional sections that render empty, so that they don't leave
+	/// runs of spaces behind. Token values are never changed
+	pub(crate) fn render_collapsed(&self, fields: &dyn Fields) -> String {
+		self.render_with(fields, true)
+	}
+
+	fn render_with(&self, fields: &dyn Fields, collapse_whitespace: bool) -> String {
+		let mut renderer = Renderer {
+			out: String::new(),
+			collapse_whitespace,
+			after_empty: collapse_whitespace,
+			literal_spaces: 0,
+		};
+		renderer.render(&self.segments, fields);
+		if renderer.after_empty {
+			let len = renderer.out.len() - renderer.literal_spaces;
+			renderer.out.truncate(len);
+		}
+		renderer.out
+	}
+}
+
+/// Output of a template being rendered
+struct Renderer {
+	out: String,
+	collapse_whitespace: bool,
+	/// Whether spaces starting the next literal would follow an empty
+	/// token rather than text
+	after_empty: bool,
+	/// Number of spaces ending `out` that came from a literal
+	literal_spaces: usize,
+}
+
+impl Renderer {
+	fn render(&mut self, segments: &[Segment], fields: &dyn Fields) {
+		for segment in segments {
+			match segment {
+				Segment::Literal(text) => {
+					let text = if self.after_empty && self.ends_in_space() {
+						text.trim_start_matches(' ')
+					} else {
+						text
+					};
+					if !text.is_empty() {
+						let trailing = text.len() - text.trim_end_matches(' ').len();
+						self.literal_spaces = if trailing == text.len() {
+							self.literal_spaces + trailing
+						} else {
+							trailing
+						};
+						self.after_empty = false;
+					}
+					self.out.push_str(text);
+				}
+				Segment::Token(name, modifier) => {
+					let len = self.out.len();
+					match (fields.value(name), modifier) {
+						(Some(value), Some(modifier)) => modifier.apply(&value, &mut self.out),
+						(Some(value), None) => self.out.push_str(&value),
+						(None, _) => {
+							self.out.push('{');
+							self.out.push_str(name);
+							if let Some(modifier) = modifier {
+								self.out.push(':');
+								self.out.push_str(modifier.name());
+							}
+							self.out.push('}');
+						}
+					}
+					self.rendered(self.out.len() > len);
+				}
+				Segment::Conditional(name, body) => {
+					if fields.present(name) {
+						self.render(body, fields);
+					} else {
+						self.rendered(false);
+					}
+				}
+			}
+		}
+	}
+
+	/// Notes whether a token or section produced any text
+	fn rendered(&mut self, produced: bool) {
+		if produced {
+			self.literal_spaces = 0;
+			self.after_empty = false;
+		} else if self.collapse_whitespace {
+			self.after_empty = true;
+		}
+	}
+
+	fn ends_in_space(&self) -> bool {
+		self.out.is_empty() || self.out.ends_with(' ')
+	}
+}
+
+fn push_literal(segments: &mut Vec<Segment>, text: &str) {
+	if text.is_empty() {
+		return;
+	}
+	match segments.last_mut() {
+		Some(Segment::Literal(literal)) => literal.push_str(text),
+		_ => segments.push(Segment::Literal(text.to_owned())),
+	}
+}
+
+/// Appends `value` to `out` escaped as the contents of a JSON string
+fn escape_json(value: &str, out: &mut String) {
+	for c in value.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+			c => out.push(c),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct TestFields;
+
+	impl Fields for TestFields {
+		fn value(&self, name: &str) -> Option<String> {
+			match name {
+				"level" => Some("INFO".to_owned()),
+				"msg" => Some("hello {file}".to_owned()),
+				"file" => Some(String::new()),
+				_ => None,
+			}
+		}
+	}
+
+	#[test]
+	fn test_render_tokens() {
+		let template = Template::parse("{level} - {msg} {unknown} {level}");
+		assert_eq!(template.render(&TestFields), "INFO - hello {file} {unknown} INFO");
+	}
+	#[test]
+	fn test_render_conditionals() {
+		let template = Template::parse("{msg}{?file} ({file}){/file}{?level} [{level}]{/level}");
+		assert_eq!(template.render(&TestFields), "hello {file} [INFO]");
+	}
+	#[test]
+	fn test_render_modifiers() {
+		let template = Template::parse(r#"{"msg":"{msg:json}"} {msg:shout} {unknown:json}"#);
+		assert_eq!(
+			template.render(&TestFields),
+			r#"{"msg":"hello {file}"} {msg:shout} {unknown:json}"#
+		);
+	}
+	#[test]
+	fn test_render_collapsed() {
+		let template = Template::parse("{file} [{level}] {file} {?file}({file}){/file} {msg} {file}");
+		assert_eq!(template.render(&TestFields), " [INFO]   hello {file} ");
+		assert_eq!(template.render_collapsed(&TestFields), "[INFO] hello {file}");
+		let template = Template::parse("{level}  {msg}");
+		assert_eq!(template.render_collapsed(&TestFields), "INFO  hello {file}");
+	}
+	#[test]
+	fn test_uses() {
+		let template = Template::parse("{level} {?file}({line}){/file} {msg:json}");
+		assert!(template.uses("line"));
+		assert!(template.uses("msg"));
+		assert!(!template.uses("file"));
+	}
+	#[test]
+	fn test_escape_json() {
+		let mut out = String::new();
+		escape_json("say \"hi\"\\\n\u{1}", &mut out);
+		assert_eq!(out, r#"say \"hi\"\\\n\u0001"#);
+	}
+	#[test]
+	fn test_parse_malformed() {
+		let template = Template::parse("{ {level}} {/msg} {?file}{msg}");
+		assert_eq!(
+			template.segments,
+			vec![
+				Segment::Literal("{ ".to_owned()),
+				Segment::Token("level".to_owned(), None),
+				Segment::Literal("} {/msg} {?file}".to_owned()),
+				Segment::Token("msg".to_owned(), None),
+			]
+		);
+	}
+}
@@ -4,234 +4,4822 @@
 //! setting and adjusting log message levels, and the
 //! format of log messages
 
+mod format;
+#[cfg(feature = "protobuf")]
+mod protobuf;
+#[cfg(unix)]
+mod shmem;
+mod time;
+
+use format::{Fields, Template};
 use log::*;
-use std::fs::OpenOptions;
-use std::io::Write;
+#[cfg(feature = "escalation")]
+use regex::Regex;
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Calls a logging helper like `Minilog::log_cat` so that its record has
+/// the calling module as its target and module path, the way records from
+/// `log!` do. Without it, helper records have the caller's file and line
+/// but no module
+///
+/// # Examples
+///
+/// ```
+/// # use log::{Level, LevelFilter};
+/// # use minilog::{here, Minilog};
+/// # use std::fs;
+/// Minilog::init(LevelFilter::Info, "minilog_here_test.txt", "[{modpath}] {file}:{line} {msg}");
+/// let line = line!() + 1;
+/// here!(Minilog::log_cat(Level::Info, "db", "slow query"));
+/// Minilog::log_cat(Level::Info, "db", "slow query");
+/// let file_contents =
+///     fs::read_to_string("minilog_here_test.txt").expect("Was unable to read file.");
+/// # fs::remove_file("minilog_here_test.txt").expect("Unable to delete test file.");
+/// let expected = format!(
+///     "[{}] {file}:{} slow query\n[] {file}:{} slow query\n",
+///     module_path!(),
+///     line,
+///     line + 1,
+///     file = file!(),
+/// );
+/// assert_eq!(file_contents, expected);
+/// ```
+///
+/// Formats set for the calling module apply to its helper records
+///
+/// ```
+/// # use log::LevelFilter;
+/// # use minilog::{here, MetricKind, Minilog};
+/// # use std::collections::BTreeMap;
+/// # use std::fs;
+/// Minilog::init(LevelFilter::Info, "minilog_here_formats_test.txt", "{level} - {msg}");
+/// let mut formats = BTreeMap::new();
+/// formats.insert(module_path!(), "METRIC {msg}");
+/// Minilog::set_target_formats(formats);
+/// here!(Minilog::log_metric("queue_depth", 3.0, MetricKind::Gauge));
+/// let file_contents =
+///     fs::read_to_string("minilog_here_formats_test.txt").expect("Was unable to read file.");
+/// # fs::remove_file("minilog_here_formats_test.txt").expect("Unable to delete test file.");
+/// assert_eq!(file_contents, "METRIC type=metric kind=gauge name=queue_depth value=3\n");
+/// ```
+#[macro_export]
+macro_rules! here {
+	($call:expr) => {
+		$crate::Minilog::in_module(module_path!(), || $call)
+	};
+}
+
+/// Clock type accepted by `Minilog::set_clock`
+type Clock = Box<dyn Fn() -> SystemTime + Send + Sync>;
+/// Context fields source accepted by `Minilog::set_context_provider`
+type ContextProvider = Box<dyn Fn() -> Vec<(String, String)> + Send + Sync>;
+/// Terminal width query accepted by `Minilog::set_width_source`
+type WidthSource = Box<dyn Fn() -> Option<usize> + Send + Sync>;
+
+/// Format string of loggers that aren't given one
+const DEFAULT_FORMAT: &str = "{level}: {msg}";
+/// How long `Follow` waits before checking the log file for new lines
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Line width assumed when the terminal width can't be determined
+const DEFAULT_WIDTH: usize = 80;
+
+/// Most files a file template target keeps open at once
+static MAX_OPEN_FILES: AtomicUsize = AtomicUsize::new(32);
+/// Whether a Minilog has been installed as the global logger
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+/// Where the installed logger writes to, locked while writing to it
+static TARGET: Mutex<Option<Target>> = Mutex::new(None);
+/// When the logger was installed, according to its clock
+static INIT_TIME: Mutex<Option<SystemTime>> = Mutex::new(None);
+/// Running total of bytes written by the logger
+static BYTES_WRITTEN: AtomicU64 = AtomicU64::new(0);
+/// Held from rendering a record until it's written, when the format
+/// string has an `{offset}` that must match where the line ends up
+static RECORD_ORDER: Mutex<()> = Mutex::new(());
+/// Source files whose records are dropped
+static MUTED_FILES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// Maximum length in bytes of a formatted line, `usize::MAX` if unlimited
+static MAX_LINE_BYTES: AtomicUsize = AtomicUsize::new(usize::MAX);
+/// Records waiting to replace the log file on the next flush, if atomic
+/// rewrites are on
+static ATOMIC_BUFFER: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+/// Encoding lines are written in
+static ENCODING: RwLock<Encoding> = RwLock::new(Encoding::Utf8);
+/// File touched while logging, to show supervisors the program is alive
+static LIVENESS: Mutex<Option<Liveness>> = Mutex::new(None);
+/// End of the warm-up period during which all records are dropped
+static WARMUP_UNTIL: Mutex<Option<SystemTime>> = Mutex::new(None);
+/// Number of this run of the program, from `set_run_counter_file`
+static RUN: AtomicU64 = AtomicU64::new(0);
+/// Lines repeated at the top of every fresh log file
+static STICKY_LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// Bytes written before each record
+static RECORD_PREFIX: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+/// Bytes written after each record's newline
+static RECORD_SUFFIX: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+/// Text appended to lines cut short by `MAX_LINE_BYTES`
+static TRUNCATION_INDICATOR: Mutex<String> = Mutex::new(String::new());
+/// Whether trailing whitespace is stripped from messages
+static TRIM_MESSAGE: AtomicBool = AtomicBool::new(false);
+/// Rendered by the `{msg}` token in place of an empty message
+static EMPTY_MESSAGE_TEXT: RwLock<String> = RwLock::new(String::new());
+/// Time source replacing `SystemTime::now`, if one was set
+static CLOCK: RwLock<Option<Clock>> = RwLock::new(None);
+/// Warnings and errors collected for the daily summary file
+static DAILY_SUMMARY: Mutex<Option<DailySummary>> = Mutex::new(None);
+/// Level applied during a daily time window
+static SCHEDULE: Mutex<Option<Schedule>> = Mutex::new(None);
+/// Socket journald listens on for native protocol entries
+#[cfg(target_os = "linux")]
+static JOURNALD_SOCKET: Mutex<Option<PathBuf>> = Mutex::new(None);
+/// Whether spaces around empty tokens are dropped
+static COLLAPSE_WHITESPACE: AtomicBool = AtomicBool::new(false);
+/// Whether messages are shortened to fit the terminal
+static AUTO_WIDTH: AtomicBool = AtomicBool::new(false);
+/// Replacement for querying the terminal width, if one was set
+static WIDTH_SOURCE: RwLock<Option<WidthSource>> = RwLock::new(None);
+/// Whether stdout and stderr are flushed after every record
+static FLUSH_STD: AtomicBool = AtomicBool::new(false);
+/// Key signing every line, if one was set
+#[cfg(feature = "signing")]
+static SIGNING_KEY: RwLock<Option<SigningKey>> = RwLock::new(None);
+/// Key encrypting everything written to a log file, if one was set
+#[cfg(feature = "encryption")]
+static ENCRYPTION_KEY: RwLock<Option<EncryptionKey>> = RwLock::new(None);
+/// Whether `init` falls back to stderr when it can't open the log file
+static FALLBACK_TO_STDERR: AtomicBool = AtomicBool::new(false);
+/// Whether records are written as length-delimited protobuf messages
+#[cfg(feature = "protobuf")]
+static PROTOBUF: AtomicBool = AtomicBool::new(false);
+/// Whether records are written as length-prefixed frames
+static FRAMED: AtomicBool = AtomicBool::new(false);
+/// Build identifier rendered by the `{build_id}` token
+static BUILD_ID: RwLock<String> = RwLock::new(String::new());
+/// Delimiter rendered by the `{sep}` token
+static FIELD_SEPARATOR: RwLock<Option<String>> = RwLock::new(None);
+/// Least severe level that still gets a `{timestamp}`
+static TIMESTAMP_LEVELS: RwLock<LevelFilter> = RwLock::new(LevelFilter::Trace);
+/// Letter case of the `{level}` token
+static LEVEL_CASE: RwLock<LevelCase> = RwLock::new(LevelCase::Upper);
+/// What to log for a record whose formatting panicked
+static ON_FORMAT_ERROR: RwLock<OnFormatError> = RwLock::new(OnFormatError::Raw);
+/// Most verbose level whose records render the location tokens
+static LOCATION_LEVELS: RwLock<LevelFilter> = RwLock::new(LevelFilter::Trace);
+/// Level temporarily replacing the logger's own level, if any
+static LEVEL_OVERRIDE: Mutex<Option<LevelOverride>> = Mutex::new(None);
+/// How long and to which level an error raises verbosity
+static ERROR_BURST: Mutex<Option<(Duration, LevelFilter)>> = Mutex::new(None);
+/// Number of records logged at each level, indexed by `Level as usize - 1`
+static LEVEL_COUNTS: [AtomicU64; 5] = [
+	AtomicU64::new(0),
+	AtomicU64::new(0),
+	AtomicU64::new(0),
+	AtomicU64::new(0),
+	AtomicU64::new(0),
+];
+/// Whether `shutdown` writes a summary of the level counts
+static EMIT_SUMMARY: AtomicBool = AtomicBool::new(false);
+/// Layout of the summary written by `shutdown`
+static SUMMARY_FORMAT: RwLock<SummaryFormat> = RwLock::new(SummaryFormat::Text);
+/// Last percentage logged by `log_progress` for each key
+static PROGRESS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+/// Percentage points between lines logged by `log_progress`
+static PROGRESS_STEP: AtomicU64 = AtomicU64::new(1);
+/// Probability of keeping a record, for levels that are sampled
+static SAMPLING: RwLock<BTreeMap<Level, f64>> = RwLock::new(BTreeMap::new());
+/// State of the random number generator used for sampling
+static SAMPLING_STATE: AtomicU64 = AtomicU64::new(0);
+/// Occurrences of every message so far, if exponential dedup is on
+static OCCURRENCES: Mutex<Option<BTreeMap<String, u64>>> = Mutex::new(None);
+/// Message patterns and the level matching records are promoted to
+#[cfg(feature = "escalation")]
+static ESCALATIONS: RwLock<Vec<(Regex, Level)>> = RwLock::new(Vec::new());
+/// Most verbose level logged by `log_cat` for each category with a filter
+static CATEGORY_LEVELS: Mutex<BTreeMap<String, LevelFilter>> = Mutex::new(BTreeMap::new());
+/// Lowest sub-level passed on by `log_sublevel`
+static MIN_SUBLEVEL: AtomicUsize = AtomicUsize::new(0);
+/// Total time spent formatting and writing records, in nanoseconds
+static LOG_COST_NANOS: AtomicU64 = AtomicU64::new(0);
+/// Number of records `LOG_COST_NANOS` was measured over
+static LOG_COST_RECORDS: AtomicU64 = AtomicU64::new(0);
+/// Number of records logged so far
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+/// Custom formatter replacing the format string, if one was set
+static FORMATTER: RwLock<Option<Box<dyn Formatter + Send + Sync>>> = RwLock::new(None);
+/// Format strings replacing the logger's own for records with these targets
+static TARGET_FORMATS: RwLock<BTreeMap<String, Template>> = RwLock::new(BTreeMap::new());
+/// Source of the fields rendered by the `{context}` token, if one was set
+static CONTEXT_PROVIDER: RwLock<Option<ContextProvider>> = RwLock::new(None);
+/// Channels that receive a copy of every logged record
+static CHANNEL_SINKS: Mutex<Vec<Sender<LogRecordOwned>>> = Mutex::new(Vec::new());
+/// WebSocket servers that broadcast every formatted line
+#[cfg(feature = "websocket")]
+static WEBSOCKET_SERVERS: Mutex<Vec<WebSocketServer>> = Mutex::new(Vec::new());
+
+thread_local! {
+	/// Time to log the current record at instead of now, set by `log_at_time`
+	static TIMESTAMP_OVERRIDE: Cell<Option<SystemTime>> = const { Cell::new(None) };
+	/// Sub-level of the current record, set by `log_sublevel`
+	static SUBLEVEL: Cell<Option<u8>> = const { Cell::new(None) };
+	/// Label set by `set_operation` for the work this thread is doing
+	static OPERATION: RefCell<String> = const { RefCell::new(String::new()) };
+	/// Logical worker label set by `set_worker_label`
+	static WORKER: RefCell<String> = const { RefCell::new(String::new()) };
+	/// Category of the current record, set by `log_cat`
+	static CATEGORY: RefCell<Option<String>> = const { RefCell::new(None) };
+	/// Module calling a logging helper through `here!`
+	static CALLER_MODULE: Cell<Option<&'static str>> = const { Cell::new(None) };
+	/// Fields of the child logger logging the current record
+	static CHILD_FIELDS: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Locks a piece of logger state, ignoring poisoning so that a panic
+/// while logging doesn't disable the logger for everyone else
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// File set with `Minilog::set_liveness_file`
+struct Liveness {
+	path: String,
+	interval: Duration,
+	touched: SystemTime,
+}
+
+impl Liveness {
+	/// Rewrites the file with the current time
+	/// # Panics
+	/// Panics if it can't write the file
+	fn touch(&mut self, now: SystemTime) {
+		if let Err(e) = fs::write(&self.path, format!("{}\n", time::format_rfc3339(now))) {
+			panic!("{}: Failed to write to liveness file {}", e, self.path);
+		}
+		self.touched = now;
+	}
+}
+
+/// Where formatted records are written
+enum Target {
+	Stdout,
+	Stderr,
+	File(String),
+	#[cfg(unix)]
+	Shmem(shmem::Ring),
+	#[cfg(target_os = "linux")]
+	Journald(std::os::unix::net::UnixDatagram, PathBuf),
+	/// Files whose paths are rendered from each record
+	FileTemplate(Template, Mutex<OpenFiles>),
+}
+
+/// Files kept open by a file template target, least recently used first
+struct OpenFiles(Vec<(String, File)>);
+
+impl OpenFiles {
+	/// Appends to the file at `path`, opening it, and closing the least
+	/// recently used file if too many are open
+	/// # Panics
+	/// Panics if it can't open the file or write to it
+	fn write(&mut self, path: String, bytes: &[u8]) {
+		let index = match self.0.iter().position(|(open, _)| *open == path) {
+			Some(index) => index,
+			None => {
+				let max_open = MAX_OPEN_FILES.load(Ordering::Relaxed).max(1);
+				if self.0.len() >= max_open {
+					self.0.drain(..=self.0.len() - max_open);
+				}
+				if let Some(parent) = Path::new(&path).parent() {
+					if let Err(e) = fs::create_dir_all(parent) {
+						panic!("{}: Failed to create directory for logfile {}", e, path);
+					}
+				}
+				match OpenOptions::new().append(true).create(true).open(&path) {
+					Ok(file) => self.0.push((path, file)),
+					Err(e) => panic!("{}: Failed to write to logfile {}", e, path),
+				}
+				self.0.len() - 1
+			}
+		};
+		let entry = self.0.remove(index);
+		self.0.push(entry);
+		let (path, file) = self.0.last_mut().unwrap();
+		append_to_log(file, path, bytes);
+	}
+}
+
+/// Bytes a signature adds to every line
+#[cfg(feature = "signing")]
+fn signature_len() -> usize {
+	match &*SIGNING_KEY.read().unwrap_or_else(|e| e.into_inner()) {
+		Some(_) => SigningKey::SIGNATURE_LEN,
+		None => 0,
+	}
+}
+
+#[cfg(not(feature = "signing"))]
+fn signature_len() -> usize {
+	0
+}
+
+/// Appends bytes for the log to one of its files. Every write to a log
+/// file goes through here, or through `file_bytes` for atomic rewrites
+/// # Panics
+/// Panics if the write fails
+fn append_to_log(file: &mut File, path: &str, bytes: &[u8]) {
+	if let Err(e) = file.write_all(&file_bytes(bytes)) {
+		panic!("{}: Failed to write to logfile {}", e, path);
+	}
+}
+
+/// Bytes as they are stored in a log file, encrypted if a key is set
+#[cfg(feature = "encryption")]
+fn file_bytes(bytes: &[u8]) -> std::borrow::Cow<'_, [u8]> {
+	match &*ENCRYPTION_KEY.read().unwrap_or_else(|e| e.into_inner()) {
+		Some(key) => key.seal(bytes).into(),
+		None => bytes.into(),
+	}
+}
+
+#[cfg(not(feature = "encryption"))]
+fn file_bytes(bytes: &[u8]) -> std::borrow::Cow<'_, [u8]> {
+	bytes.into()
+}
+/// Connected client of a WebSocket server
+#[cfg(feature = "websocket")]
+type WebSocketClient = tungstenite::WebSocket<std::net::TcpStream>;
+
+/// How long a WebSocket client may hold up broadcasting before it is dropped
+#[cfg(feature = "websocket")]
+const WEBSOCKET_WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// WebSocket server started by `add_websocket`
+#[cfg(feature = "websocket")]
+struct WebSocketServer {
+	/// Lines for the server's sender thread to broadcast
+	lines: Sender<String>,
+	clients: std::sync::Arc<Mutex<Vec<WebSocketClient>>>,
+}
+
+impl Target {
+	fn new(name: &str) -> Target {
+		match name {
+			"stdout" => Target::Stdout,
+			"stderr" => Target::Stderr,
+			path => Target::File(path.to_owned()),
+		}
+	}
+
+	/// Byte position the next record will start at. Targets other than a
+	/// single file count the bytes written to them
+	fn offset(&self) -> u64 {
+		match self {
+			Target::File(path) => match lock(&ATOMIC_BUFFER).as_ref() {
+				Some(buffer) => buffer.len() as u64,
+				None => fs::metadata(path).map_or(0, |metadata| metadata.len()),
+			},
+			_ => BYTES_WRITTEN.load(Ordering::Relaxed),
+		}
+	}
+
+	/// Writes a finished record, returning whether it was written
+	/// # Panics
+	/// Panics if it can't open the file or write to it
+	fn write(&self, bytes: &[u8]) -> bool {
+		// A closed pipe on stdout or stderr mustn't take the program down
+		// with it, so failed writes to them are dropped silently
+		match self {
+			Target::Stdout => write_std(&mut std::io::stdout().lock(), bytes),
+			Target::Stderr => write_std(&mut std::io::stderr().lock(), bytes),
+			#[cfg(unix)]
+			Target::Shmem(ring) => ring.push(bytes),
+			#[cfg(target_os = "linux")]
+			Target::Journald(socket, path) => socket.send_to(bytes, path).is_ok(),
+			Target::FileTemplate(_, files) => {
+				// Lines that don't come from a record go to every open file
+				for (path, file) in lock(files).0.iter_mut() {
+					append_to_log(file, path, bytes);
+				}
+				true
+			}
+			Target::File(path) => {
+				if let Some(buffer) = lock(&ATOMIC_BUFFER).as_mut() {
+					buffer.extend_from_slice(&file_bytes(bytes));
+					return true;
+				}
+				let mut file = OpenOptions::new()
+					.read(true)
+					.append(true)
+					.create(true)
+					.open(path);
+				match &mut file {
+					Ok(file) => {
+						append_to_log(file, path, bytes);
+						true
+					}
+					Err(e) => panic!("{}: Failed to write to logfile {}", e, path),
+				}
+			}
+		}
+	}
+}
+
+/// Encodes a record for targets with a format of their own, like journald
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+fn native_entry(record: &Record, message: &str) -> Option<Vec<u8>> {
+	match lock(&TARGET).as_ref() {
+		#[cfg(target_os = "linux")]
+		Some(Target::Journald(..)) => Some(journal_entry(record, message)),
+		_ => None,
+	}
+}
+
+/// Encodes a record as a length-delimited protobuf message, if protobuf
+/// output is on. A `fallback` message, for records whose formatting
+/// panicked, replaces the record's message and fields
+#[cfg_attr(not(feature = "protobuf"), allow(unused_variables))]
+fn protobuf_entry(
+	record: &Record,
+	timestamp: SystemTime,
+	fallback: Option<&str>,
+) -> Option<Vec<u8>> {
+	#[cfg(feature = "protobuf")]
+	if PROTOBUF.load(Ordering::Relaxed) {
+		let (message, fields) = match fallback {
+			Some(message) => (message.to_owned(), Vec::new()),
+			None => (RecordFields::new(record, timestamp).message, context_fields()),
+		};
+		let message = protobuf::Message {
+			level: record.level() as u32,
+			timestamp_millis: time::unix_millis(timestamp),
+			message,
+			fields,
+		};
+		return Some(message.encode_delimited());
+	}
+	None
+}
+
+/// Encodes a record as a journal entry in journald's native protocol, with
+/// the formatted line as its message
+#[cfg(target_os = "linux")]
+fn journal_entry(record: &Record, message: &str) -> Vec<u8> {
+	let priority = match record.level() {
+		Level::Error => "3",
+		Level::Warn => "4",
+		Level::Info => "6",
+		Level::Debug | Level::Trace => "7",
+	};
+	let line = record.line().map(|line| line.to_string());
+	let fields = [
+		("PRIORITY", Some(priority)),
+		("MESSAGE", Some(message)),
+		("TARGET", Some(record.target())),
+		("CODE_MODULE", record.module_path()),
+		("CODE_FILE", record.file()),
+		("CODE_LINE", line.as_deref()),
+	];
+	let mut entry = Vec::new();
+	for (name, value) in fields {
+		let value = match value {
+			Some(value) => value,
+			None => continue,
+		};
+		entry.extend_from_slice(name.as_bytes());
+		if value.contains('\n') {
+			// Values with newlines are sent as their length followed by
+			// the raw value
+			entry.push(b'\n');
+			entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+		} else {
+			entry.push(b'=');
+		}
+		entry.extend_from_slice(value.as_bytes());
+		entry.push(b'\n');
+	}
+	entry
+}
+
+/// Writes to stdout or stderr, flushing afterwards if `set_flush_std` is on
+fn write_std(out: &mut dyn Write, bytes: &[u8]) -> bool {
+	out.write_all(bytes).is_ok() && (!FLUSH_STD.load(Ordering::Relaxed) || out.flush().is_ok())
+}
+
+/// Writes to the installed logger's target, returning whether anything
+/// was written
+fn write_output(bytes: &[u8]) -> bool {
+	match lock(&TARGET).as_ref() {
+		Some(target) => target.write(bytes),
+		None => false,
+	}
+}
+
+/// Logs a record for a logging helper, with the file and line of the
+/// helper's caller and the module it was called from with `here!`
+#[track_caller]
+fn log_from_caller(level: Level, args: fmt::Arguments) {
+	if level > STATIC_MAX_LEVEL || level > max_level() {
+		return;
+	}
+	let location = panic::Location::caller();
+	let module = CALLER_MODULE.with(Cell::get);
+	logger().log(
+		&Record::builder()
+			.args(args)
+			.level(level)
+			.target(module.unwrap_or(""))
+			.module_path_static(module)
+			.file_static(Some(location.file()))
+			.line(Some(location.line()))
+			.build(),
+	);
+}
+
+/// Encodes a finished line, ending it in a newline and surrounding it with
+/// the record prefix and suffix
+fn line_bytes(line: &str) -> Vec<u8> {
+	let encoding = *ENCODING.read().unwrap_or_else(|e| e.into_inner());
+	let mut bytes = lock(&RECORD_PREFIX).clone();
+	bytes.extend(encoding.encode(line));
+	bytes.extend(encoding.encode("\n"));
+	bytes.extend_from_slice(&lock(&RECORD_SUFFIX));
+	bytes
+}
+
+/// Encodes a record's finished line for the target: in the target's own
+/// format, as its protobuf message or as a frame if one of those is in use,
+/// and as a line otherwise
+fn record_bytes(record: &Record, line: &str, protobuf: Option<Vec<u8>>) -> Vec<u8> {
+	if let Some(entry) = native_entry(record, line).or(protobuf) {
+		entry
+	} else if FRAMED.load(Ordering::Relaxed) {
+		let mut frame = (line.len() as u32).to_be_bytes().to_vec();
+		frame.extend_from_slice(line.as_bytes());
+		frame
+	} else {
+		line_bytes(line)
+	}
+}
+
+/// Encodes a line that doesn't come from a record, like a sticky line, as
+/// if it were the message of an info record: signed, and framed or in
+/// protobuf if records are
+fn unrecorded_line_bytes(line: &str) -> Vec<u8> {
+	let args = format_args!("{}", line);
+	let record = Record::builder()
+		.args(args)
+		.level(Level::Info)
+		.target("minilog")
+		.build();
+	let protobuf = protobuf_entry(&record, now(), Some(line));
+	#[cfg_attr(not(feature = "signing"), allow(unused_mut))]
+	let mut line = line.to_owned();
+	#[cfg(feature = "signing")]
+	if let Some(key) = &*SIGNING_KEY.read().unwrap_or_else(|e| e.into_inner()) {
+		key.sign(&mut line);
+	}
+	record_bytes(&record, &line, protobuf)
+}
+
+/// Writes a record's bytes to the installed logger's target, returning
+/// whether anything was written
+fn write_record(record: &Record, timestamp: SystemTime, bytes: &[u8]) -> bool {
+	match lock(&TARGET).as_ref() {
+		Some(target @ Target::FileTemplate(path, files)) => {
+			let mut fields = RecordFields::new(record, timestamp);
+			fields.offset = Some(target.offset());
+			let path = path.render(&PathFields(fields));
+			lock(files).write(path, bytes);
+			true
+		}
+		Some(target) => target.write(bytes),
+		None => false,
+	}
+}
+
+/// Width in columns available to a line, for auto width
+fn terminal_width() -> usize {
+	let width = match &*WIDTH_SOURCE.read().unwrap_or_else(|e| e.into_inner()) {
+		Some(source) => source(),
+		None => match lock(&TARGET).as_ref() {
+			Some(Target::Stdout) => tty_width(1),
+			Some(Target::Stderr) => tty_width(2),
+			_ => None,
+		},
+	};
+	width.unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Width of the terminal behind a file descriptor, `None` if it isn't one
+#[cfg(unix)]
+fn tty_width(fd: libc::c_int) -> Option<usize> {
+	// SAFETY: TIOCGWINSZ only writes a winsize into the struct it's given
+	let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+	if unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_col > 0 {
+		Some(size.ws_col as usize)
+	} else {
+		None
+	}
+}
+
+#[cfg(not(unix))]
+fn tty_width(_fd: i32) -> Option<usize> {
+	None
+}
+
+/// Most severe level an escalation rule promotes `record` to, if it is
+/// more severe than the record's own
+#[cfg(feature = "escalation")]
+fn escalated_level(record: &Record) -> Option<Level> {
+	let escalations = ESCALATIONS.read().unwrap_or_else(|e| e.into_inner());
+	if escalations.is_empty() {
+		return None;
+	}
+	let msg = record.args().to_string();
+	escalations
+		.iter()
+		.filter(|(pattern, level)| *level < record.level() && pattern.is_match(&msg))
+		.map(|&(_, level)| level)
+		.min()
+}
+
+#[cfg(not(feature = "escalation"))]
+fn escalated_level(_: &Record) -> Option<Level> {
+	None
+}
+
+/// Decides whether a record at `level` survives severity sampling
+fn sampled(level: Level) -> bool {
+	match SAMPLING.read().unwrap_or_else(|e| e.into_inner()).get(&level) {
+		Some(&keep) => random_unit() < keep,
+		None => true,
+	}
+}
+
+/// Whether a record survives exponential dedup, which only keeps the
+/// occurrences of a message that are a power of two
+fn deduped(record: &Record) -> bool {
+	match lock(&OCCURRENCES).as_mut() {
+		Some(occurrences) => {
+			let count = occurrences.entry(record.args().to_string()).or_insert(0);
+			*count += 1;
+			count.is_power_of_two()
+		}
+		None => true,
+	}
+}
+
+/// Fields of the current child logger followed by the context provider's
+fn context_fields() -> Vec<(String, String)> {
+	let mut fields = CHILD_FIELDS.with(|fields| fields.borrow().clone());
+	if let Some(provider) = &*CONTEXT_PROVIDER.read().unwrap_or_else(|e| e.into_inner()) {
+		fields.extend(provider());
+	}
+	fields
+}
+
+/// Random number in `[0, 1)`, from a SplitMix64 generator
+fn random_unit() -> f64 {
+	const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+	let mut z = SAMPLING_STATE.fetch_add(GAMMA, Ordering::Relaxed).wrapping_add(GAMMA);
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^= z >> 31;
+	(z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Current time according to the logger's clock
+fn now() -> SystemTime {
+	match &*CLOCK.read().unwrap_or_else(|e| e.into_inner()) {
+		Some(clock) => clock(),
+		None => SystemTime::now(),
+	}
+}
+
+/// Appends `contents` to the file at `path`, creating it if needed
+fn append_to_file(path: &str, contents: &str) -> std::io::Result<()> {
+	OpenOptions::new()
+		.append(true)
+		.create(true)
+		.open(path)?
+		.write_all(contents.as_bytes())
+}
+
+/// Unique warning and error messages logged during one day, with counts
+struct DailySummary {
+	path: String,
+	day: i64,
+	counts: BTreeMap<(Level, String), u64>,
+}
 
-/// Consists of name for path of file to log to, and string
-/// which serves as a format string for log messages
+impl DailySummary {
+	/// Counts a record, first writing out the previous day's summary if
+	/// the day has changed
+	fn record(&mut self, record: &Record) {
+		let today = time::unix_day(now());
+		if today != self.day {
+			self.write();
+			self.counts.clear();
+			self.day = today;
+		}
+		if record.level() <= Level::Warn {
+			*self
+				.counts
+				.entry((record.level(), record.args().to_string()))
+				.or_insert(0) += 1;
+		}
+	}
+
+	/// Appends the summary for the current day to the summary file
+	/// # Panics
+	/// Panics if it can't write to the summary file
+	fn write(&self) {
+		if self.counts.is_empty() {
+			return;
+		}
+		let mut summary = format!("Summary for {}\n", time::format_date(self.day));
+		for ((level, msg), count) in &self.counts {
+			summary.push_str(&format!("{} x{}: {}\n", level, count, msg));
+		}
+		if let Err(e) = append_to_file(&self.path, &summary) {
+			panic!("{}: Failed to write to summary file {}", e, &self.path);
+		}
+	}
+}
+
+/// A level that applies between two times of day, in seconds since
+/// midnight UTC. The window wraps past midnight if `end` is before `start`
+struct Schedule {
+	start: u64,
+	end: u64,
+	level: LevelFilter,
+}
+
+impl Schedule {
+	/// Returns the scheduled level if `time` falls within the window
+	fn level_at(&self, time: SystemTime) -> Option<LevelFilter> {
+		let secs = time::unix_secs(time).rem_euclid(time::SECS_PER_DAY) as u64;
+		let inside = if self.start <= self.end {
+			self.start <= secs && secs < self.end
+		} else {
+			secs >= self.start || secs < self.end
+		};
+		if inside {
+			Some(self.level)
+		} else {
+			None
+		}
+	}
+}
+
+/// A more verbose level that is in effect until `until`, after which the
+/// logger goes back to `restore`
+struct LevelOverride {
+	level: LevelFilter,
+	until: SystemTime,
+	restore: LevelFilter,
+}
+
+/// Raises the logger's level to at least `level` until `until`. Raising
+/// it again while already raised extends the window
+fn raise_level_until(level: LevelFilter, until: SystemTime) {
+	let mut level_override = lock(&LEVEL_OVERRIDE);
+	let raised = match level_override.take() {
+		Some(current) => LevelOverride {
+			level: current.level.max(level),
+			until: current.until.max(until),
+			restore: current.restore,
+		},
+		None => LevelOverride {
+			level,
+			until,
+			restore: max_level(),
+		},
+	};
+	set_max_level(raised.restore.max(raised.level));
+	*level_override = Some(raised);
+}
+
+/// Puts the logger's own level back once a raised level has run out
+fn expire_level_override(now: SystemTime) {
+	let mut level_override = lock(&LEVEL_OVERRIDE);
+	if let Some(current) = level_override.as_ref() {
+		if now >= current.until {
+			set_max_level(current.restore);
+			*level_override = None;
+		}
+	}
+}
+
+/// Quotes a key=value field value if it would otherwise be ambiguous
+fn kv_value(value: &str) -> String {
+	if !value.is_empty() && !value.contains(|c: char| c == ' ' || c == '"' || c == '=' || c.is_control()) {
+		return value.to_owned();
+	}
+	format!("{:?}", value)
+}
+
+/// Cuts `line` down to at most `max` bytes on a char boundary, ending it
+/// with `indicator` if it fits
+fn truncate_line(line: &mut String, max: usize, indicator: &str) {
+	if line.len() <= max {
+		return;
+	}
+	let (keep, indicator) = match max.checked_sub(indicator.len()) {
+		Some(keep) => (keep, indicator),
+		None => (max, ""),
+	};
+	let mut end = keep;
+	while !line.is_char_boundary(end) {
+		end -= 1;
+	}
+	line.truncate(end);
+	line.push_str(indicator);
+}
+
+/// Returns whether `file` is `pattern` or a path ending in it
+fn path_matches(file: &str, pattern: &str) -> bool {
+	file.strip_suffix(pattern)
+		.is_some_and(|rest| rest.is_empty() || rest.ends_with('/') || rest.ends_with('\\'))
+}
+
+/// Escapes a token value for use in a path, so that log data can't reach
+/// outside the directory a file template names, by replacing path
+/// separators, `..` and NUL with underscores
+fn path_component(value: &str) -> String {
+	value.replace("..", "__").replace(['/', '\\', '\0'], "_")
+}
+
+/// Consists of the parsed format string for log messages
 pub struct Minilog {
-	logfile_name: String,
-	fmt_string: String,
+	template: Template,
 }
 
-impl Minilog {
-	/// Initializes the logger, must be called before attempting
-	/// to write log messages
+/// Token values for the record being logged
+struct RecordFields<'a> {
+	record: &'a Record<'a>,
+	timestamp: SystemTime,
+	message: String,
+	/// Value of the `{offset}` token, for callers already holding the
+	/// target's lock
+	offset: Option<u64>,
+}
+
+impl<'a> RecordFields<'a> {
+	fn new(record: &'a Record<'a>, timestamp: SystemTime) -> RecordFields<'a> {
+		let mut message = record.args().to_string();
+		if TRIM_MESSAGE.load(Ordering::Relaxed) {
+			message.truncate(message.trim_end().len());
+		}
+		if message.is_empty() {
+			message = EMPTY_MESSAGE_TEXT.read().unwrap_or_else(|e| e.into_inner()).clone();
+		}
+		RecordFields {
+			record,
+			timestamp,
+			message,
+			offset: None,
+		}
+	}
+}
+
+/// Every token `RecordFields` renders, with a short description
+const TOKENS: &[(&str, &str)] = &[
+	("level", "level of the record, e.g. INFO"),
+	("msg", "the logged message"),
+	("modpath", "module the record came from"),
+	("file", "source file the record came from"),
+	("line", "source line the record came from"),
+	("timestamp", "RFC 3339 time the record was logged"),
+	("build_id", "build identifier set with set_build_id"),
+	("sep", "field separator set with set_field_separator"),
+	("category", "category given to log_cat"),
+	("operation", "operation set on the logging thread"),
+	("sublevel", "sub-level given to log_sublevel"),
+	("worker", "worker label set on the logging thread"),
+	("run", "run number counted with set_run_counter_file"),
+	("context", "key=value fields from the context provider"),
+	("offset", "byte position the line starts at in the file"),
+];
+
+impl RecordFields<'_> {
+	/// Whether the record's level is verbose enough for `{file}` and `{line}`
+	fn shows_location(&self) -> bool {
+		self.record.level() <= *LOCATION_LEVELS.read().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl Fields for RecordFields<'_> {
+	fn value(&self, name: &str) -> Option<String> {
+		let record = self.record;
+		Some(match name {
+			"level" => LEVEL_CASE.read().unwrap_or_else(|e| e.into_inner()).apply(record.level()),
+			"msg" => self.message.clone(),
+			"modpath" => record.module_path().unwrap_or("").to_owned(),
+			"file" | "line" if !self.shows_location() => String::new(),
+			"file" => record.file().unwrap_or("").to_owned(),
+			"line" => record.line().unwrap_or(0).to_string(),
+			"build_id" => BUILD_ID.read().unwrap_or_else(|e| e.into_inner()).clone(),
+			"sep" => FIELD_SEPARATOR
+				.read()
+				.unwrap_or_else(|e| e.into_inner())
+				.as_deref()
+				.unwrap_or(" - ")
+				.to_owned(),
+			"category" => CATEGORY.with(|category| category.borrow().clone().unwrap_or_default()),
+			"context" => context_fields()
+				.iter()
+				.map(|(key, value)| format!("{}={}", key, kv_value(value)))
+				.collect::<Vec<_>>()
+				.join(" "),
+			"offset" => match self.offset {
+				Some(offset) => offset,
+				None => lock(&TARGET).as_ref().map_or(0, Target::offset),
+			}
+			.to_string(),
+			"run" => match RUN.load(Ordering::Relaxed) {
+				0 => String::new(),
+				run => run.to_string(),
+			},
+			"worker" => WORKER.with(|worker| worker.borrow().clone()),
+			"operation" => OPERATION.with(|operation| operation.borrow().clone()),
+			"sublevel" => SUBLEVEL
+				.with(Cell::get)
+				.map(|sublevel| sublevel.to_string())
+				.unwrap_or_default(),
+			"timestamp" => {
+				if record.level() <= *TIMESTAMP_LEVELS.read().unwrap_or_else(|e| e.into_inner()) {
+					time::format_rfc3339(self.timestamp)
+				} else {
+					String::new()
+				}
+			}
+			_ => return None,
+		})
+	}
+
+	fn present(&self, name: &str) -> bool {
+		match name {
+			"line" => self.shows_location() && self.record.line().is_some(),
+			_ => self.value(name).is_some_and(|value| !value.is_empty()),
+		}
+	}
+}
+
+/// Token values for a file template's path, escaped with `path_component`
+struct PathFields<'a>(RecordFields<'a>);
+
+impl Fields for PathFields<'_> {
+	fn value(&self, name: &str) -> Option<String> {
+		self.0.value(name).map(|value| path_component(&value))
+	}
+
+	fn present(&self, name: &str) -> bool {
+		self.0.present(name)
+	}
+}
+
+/// Formats records in place of the format string passed to `init`, for
+/// full control over the layout of each line
+///
+/// # Examples
+///
+/// ```
+/// # use log::{warn, LevelFilter, Record};
+/// # use minilog::{Formatter, Minilog, RenderMeta};
+/// # use std::fs;
+/// struct Bracketed;
+///
+/// impl Formatter for Bracketed {
+///     fn format(&self, record: &Record, meta: &RenderMeta) -> String {
+///         format!("#{} [{}] <{}>", meta.sequence, record.level(), record.args())
+///     }
+/// }
+///
+/// Minilog::init(LevelFilter::Info, "minilog_formatter_test.txt", "{level} - {msg}");
+/// Minilog::set_formatter(Some(Box::new(Bracketed)));
+/// warn!("first");
+/// warn!("second");
+/// let file_contents =
+///     fs::read_to_string("minilog_formatter_test.txt").expect("Was unable to read file.");
+/// # fs::remove_file("minilog_formatter_test.txt").expect("Unable to delete test file.");
+/// assert_eq!(file_contents, "#0 [WARN] <first>\n#1 [WARN] <second>\n");
+/// ```
+pub trait Formatter {
+	/// Returns the line to log for `record`, without a trailing newline
+	fn format(&self, record: &Record, meta: &RenderMeta) -> String;
+}
+
+/// Details about a record that aren't part of `Record` itself, passed
+/// to a [`Formatter`]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct RenderMeta {
+	/// Time the record was logged
+	pub timestamp: SystemTime,
+	/// Number of records logged before this one
+	pub sequence: u64,
+	/// Id of the logging process
+	pub pid: u32,
+}
+
+/// Owned snapshot of a logged record, which unlike `Record` can be sent
+/// to other threads
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecordOwned {
+	/// Level of the record
+	pub level: Level,
+	/// Target of the record
+	pub target: String,
+	/// The formatted message, without the rest of the format string
+	pub message: String,
+	/// Time the record was logged
+	pub timestamp: SystemTime,
+	/// Module the record came from, if known
+	pub module_path: Option<String>,
+	/// Source file the record came from, if known
+	pub file: Option<String>,
+	/// Line the record came from, if known
+	pub line: Option<u32>,
+	/// Category given to `Minilog::log_cat`, if any
+	pub category: Option<String>,
+}
+
+impl LogRecordOwned {
+	fn new(record: &Record, timestamp: SystemTime) -> LogRecordOwned {
+		LogRecordOwned {
+			level: record.level(),
+			target: record.target().to_owned(),
+			message: record.args().to_string(),
+			timestamp,
+			module_path: record.module_path().map(str::to_owned),
+			file: record.file().map(str::to_owned),
+			line: record.line(),
+			category: CATEGORY.with(|category| category.borrow().clone()),
+		}
+	}
+}
+
+/// Iterator over the records in a stream written with framing on,
+/// created by [`Minilog::read_frames`]
+pub struct Frames<R> {
+	reader: R,
+	failed: bool,
+}
+
+impl<R: Read> Frames<R> {
+	fn read_frame(&mut self) -> Option<io::Result<String>> {
+		let mut header = [0; 4];
+		let mut filled = 0;
+		while filled < header.len() {
+			match self.reader.read(&mut header[filled..]) {
+				Ok(0) if filled == 0 => return None,
+				Ok(0) => return Some(Err(io::ErrorKind::UnexpectedEof.into())),
+				Ok(n) => filled += n,
+				Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+				Err(e) => return Some(Err(e)),
+			}
+		}
+		// Reading through `take` only allocates as much as the stream holds,
+		// whatever length a corrupt header claims
+		let len = u64::from(u32::from_be_bytes(header));
+		let mut payload = Vec::new();
+		match self.reader.by_ref().take(len).read_to_end(&mut payload) {
+			Ok(read) if read as u64 == len => {}
+			Ok(_) => return Some(Err(io::ErrorKind::UnexpectedEof.into())),
+			Err(e) => return Some(Err(e)),
+		}
+		Some(String::from_utf8(payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
+	}
+}
+
+/// A record decoded from protobuf output, created by
+/// [`Minilog::read_protobuf`]
+#[cfg(feature = "protobuf")]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ProtobufRecord {
+	/// Level of the record
+	pub level: Level,
+	/// Time the record was logged, to the millisecond
+	pub timestamp: SystemTime,
+	/// The message, without the rest of the format string
+	pub message: String,
+	/// Fields from the child logger and context provider
+	pub fields: Vec<(String, String)>,
+}
+
+/// Iterator over the records in a stream written with protobuf output on,
+/// created by [`Minilog::read_protobuf`]
+#[cfg(feature = "protobuf")]
+pub struct ProtobufRecords<R> {
+	reader: R,
+	failed: bool,
+}
+
+#[cfg(feature = "protobuf")]
+impl<R: Read> ProtobufRecords<R> {
+	fn read_record(&mut self) -> Option<io::Result<ProtobufRecord>> {
+		let mut header = Vec::new();
+		loop {
+			let mut byte = [0];
+			match self.reader.read(&mut byte) {
+				Ok(0) if header.is_empty() => return None,
+				Ok(0) => return Some(Err(io::ErrorKind::UnexpectedEof.into())),
+				Ok(_) => {
+					header.push(byte[0]);
+					if byte[0] < 0x80 {
+						break;
+					}
+				}
+				Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+				Err(e) => return Some(Err(e)),
+			}
+		}
+		let len = match protobuf::read_varint(&mut &header[..]) {
+			Ok(len) => len,
+			Err(e) => return Some(Err(e)),
+		};
+		let mut body = Vec::new();
+		match self.reader.by_ref().take(len).read_to_end(&mut body) {
+			Ok(read) if read as u64 == len => {}
+			Ok(_) => return Some(Err(io::ErrorKind::UnexpectedEof.into())),
+			Err(e) => return Some(Err(e)),
+		}
+		Some(protobuf::Message::decode(&body).and_then(|message| {
+			let level = match message.level {
+				1 => Level::Error,
+				2 => Level::Warn,
+				3 => Level::Info,
+				4 => Level::Debug,
+				5 => Level::Trace,
+				_ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown level")),
+			};
+			let since_epoch = Duration::from_millis(message.timestamp_millis.unsigned_abs());
+			Ok(ProtobufRecord {
+				level,
+				timestamp: if message.timestamp_millis < 0 {
+					SystemTime::UNIX_EPOCH - since_epoch
+				} else {
+					SystemTime::UNIX_EPOCH + since_epoch
+				},
+				message: message.message,
+				fields: message.fields,
+			})
+		}))
+	}
+}
+
+#[cfg(feature = "protobuf")]
+impl<R: Read> Iterator for ProtobufRecords<R> {
+	type Item = io::Result<ProtobufRecord>;
+
+	/// Returns the next record, or an error if the stream ends partway
+	/// through a message or holds something else. Iteration stops after
+	/// the first error
+	fn next(&mut self) -> Option<io::Result<ProtobufRecord>> {
+		if self.failed {
+			return None;
+		}
+		let record = self.read_record();
+		self.failed = matches!(record, Some(Err(_)));
+		record
+	}
+}
+
+/// Blocking iterator over the lines written to the log file, which keeps
+/// following the file when it is replaced, created by [`Minilog::follow`]
+pub struct Follow {
+	path: PathBuf,
+	file: File,
+	id: Option<(u64, u64)>,
+	pending: Vec<u8>,
+}
+
+impl Follow {
+	/// Takes the next complete line out of what has been read so far
+	fn take_line(&mut self) -> Option<String> {
+		let end = self.pending.iter().position(|&byte| byte == b'\n')?;
+		let line = String::from_utf8_lossy(&self.pending[..end]).into_owned();
+		self.pending.drain(..=end);
+		Some(line)
+	}
+
+	/// Reads whatever has been appended to the followed file, returning
+	/// whether anything was
+	fn read_more(&mut self) -> bool {
+		let mut buf = [0; 4096];
+		match self.file.read(&mut buf) {
+			Ok(read) if read > 0 => {
+				self.pending.extend_from_slice(&buf[..read]);
+				true
+			}
+			_ => false,
+		}
+	}
+
+	/// Switches to a new file at the followed path if the old one has been
+	/// replaced or truncated, returning whether it did
+	fn reopen_if_replaced(&mut self) -> bool {
+		let metadata = match fs::metadata(&self.path) {
+			Ok(metadata) => metadata,
+			// Moved away and not recreated yet
+			Err(_) => return false,
+		};
+		let position = self.file.stream_position().unwrap_or(0);
+		if file_id(&metadata) == self.id && metadata.len() >= position {
+			return false;
+		}
+		// The old file isn't written to anymore, so nothing after this is
+		// lost by switching
+		while self.read_more() {}
+		match File::open(&self.path) {
+			Ok(file) => {
+				self.id = file.metadata().ok().as_ref().and_then(file_id);
+				self.file = file;
+				true
+			}
+			Err(_) => false,
+		}
+	}
+}
+
+impl Iterator for Follow {
+	type Item = String;
+
+	/// Blocks until the next line is written, so it never returns `None`
+	fn next(&mut self) -> Option<String> {
+		loop {
+			if let Some(line) = self.take_line() {
+				return Some(line);
+			}
+			if !self.read_more() && !self.reopen_if_replaced() {
+				std::thread::sleep(FOLLOW_POLL_INTERVAL);
+			}
+		}
+	}
+}
+
+/// Reader for the records logged to a shared memory segment by
+/// [`Minilog::init_shmem`], possibly in another process, created by
+/// [`Minilog::open_shmem`]
+#[cfg(unix)]
+pub struct ShmemReader {
+	ring: shmem::Ring,
+	position: u64,
+	lost: u64,
+}
+
+#[cfg(unix)]
+impl ShmemReader {
+	/// Returns how many times records were overwritten before they could
+	/// be read, because the reader fell a whole segment behind
+	pub fn lost(&self) -> u64 {
+		self.lost
+	}
+}
+
+#[cfg(unix)]
+impl Iterator for ShmemReader {
+	type Item = String;
+
+	/// Returns the next formatted line, or `None` once the reader has
+	/// caught up. Calling it again later returns lines logged since
+	fn next(&mut self) -> Option<String> {
+		loop {
+			let (bytes, next) = self.ring.read(self.position)?;
+			self.position = next;
+			match bytes {
+				Some(bytes) => {
+					let line = String::from_utf8_lossy(&bytes);
+					return Some(line.strip_suffix('\n').unwrap_or(&line).to_owned());
+				}
+				None => self.lost += 1,
+			}
+		}
+	}
+}
+
+/// Identity of the file behind some metadata, to tell a replaced file
+/// from the original
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+	use std::os::unix::fs::MetadataExt;
+	Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+	None
+}
+
+impl<R: Read> Iterator for Frames<R> {
+	type Item = io::Result<String>;
+
+	/// Returns the next record, or an error if the stream ends partway
+	/// through a frame. Iteration stops after the first error
+	fn next(&mut self) -> Option<io::Result<String>> {
+		if self.failed {
+			return None;
+		}
+		let frame = self.read_frame();
+		self.failed = matches!(frame, Some(Err(_)));
+		frame
+	}
+}
+
+/// Layout of the summary line written by [`Minilog::shutdown`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryFormat {
+	/// `SUMMARY errors=1 warnings=2 info=3 debug=0 trace=0`
+	Text,
+	/// `{"summary":{"errors":1,"warnings":2,"info":3,"debug":0,"trace":0}}`
+	Json,
+}
+
+/// Reason initializing a Minilog failed
+///
+/// # Examples
+///
+/// ```
+/// # use log::{LevelFilter, Log, Metadata, Record};
+/// # use minilog::{InitError, Minilog};
+/// struct OtherLogger;
+/// impl Log for OtherLogger {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, _: &Record) {}
+///     fn flush(&self) {}
+/// }
+/// log::set_logger(&OtherLogger).expect("Was unable to install the other logger.");
+/// let result = Minilog::init(LevelFilter::Info, "stderr", "{msg}");
+/// assert_eq!(result, Err(InitError::ForeignLoggerInstalled));
+/// ```
+///
+/// ```
+/// # use log::LevelFilter;
+/// # use minilog::{InitError, Minilog};
+/// Minilog::init(LevelFilter::Info, "stderr", "{msg}").expect("Was unable to initialize.");
+/// let result = Minilog::init(LevelFilter::Info, "stderr", "{msg}");
+/// assert_eq!(result, Err(InitError::AlreadyInitialized));
+/// ```
+///
+/// ```
+/// # use log::LevelFilter;
+/// # use minilog::{InitError, Minilog};
+/// # use std::thread;
+/// let inits: Vec<_> = (0..8)
+///     .map(|_| thread::spawn(|| Minilog::init(LevelFilter::Info, "stderr", "{msg}")))
+///     .collect();
+/// let mut results: Vec<_> = inits.into_iter().map(|init| init.join().unwrap()).collect();
+/// results.retain(|result| result.is_err());
+/// assert_eq!(results, [Err(InitError::AlreadyInitialized); 7]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InitError {
+	/// A Minilog was already initialized; only one can be per program
+	AlreadyInitialized,
+	/// A logger other than Minilog was installed first, often by a
+	/// dependency that sets up its own logging
+	ForeignLoggerInstalled,
+	/// The target couldn't be set up, e.g. a shared memory segment that
+	/// couldn't be created, for the reason given
+	TargetUnavailable(io::ErrorKind),
+}
+
+impl fmt::Display for InitError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			InitError::AlreadyInitialized => f.write_str("Minilog was already initialized"),
+			InitError::ForeignLoggerInstalled => f.write_str(
+				"another logger was installed before Minilog; initialize Minilog \
+				 first, or turn off the logger of the crate that installs it",
+			),
+			InitError::TargetUnavailable(kind) => {
+				write!(f, "the log target couldn't be set up: {}", io::Error::from(*kind))
+			}
+		}
+	}
+}
+
+impl std::error::Error for InitError {}
+
+/// Letter case of the `{level}` token, set with [`Minilog::set_level_case`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelCase {
+	/// `INFO`, the default
+	Upper,
+	/// `info`
+	Lower,
+	/// `Info`
+	Title,
+}
+
+impl LevelCase {
+	fn apply(self, level: Level) -> String {
+		let upper = level.as_str();
+		match self {
+			LevelCase::Upper => upper.to_owned(),
+			LevelCase::Lower => upper.to_ascii_lowercase(),
+			LevelCase::Title => upper[..1].to_owned() + &upper[1..].to_ascii_lowercase(),
+		}
+	}
+}
+
+/// What to log for a record whose formatting panicked, in a [`Formatter`]
+/// or a context provider, set with [`Minilog::set_on_format_error`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OnFormatError {
+	/// Logs nothing for the record
+	Skip,
+	/// Logs the message alone, the default
+	Raw,
+	/// Logs the given text in place of the line
+	Placeholder(String),
+}
+
+/// Secret key for signing lines with HMAC-SHA256, set with
+/// [`Minilog::set_signing_key`]. Its `Debug` output leaves the key out
+#[cfg(feature = "signing")]
+#[derive(Clone)]
+pub struct SigningKey(Vec<u8>);
+
+#[cfg(feature = "signing")]
+impl SigningKey {
+	/// Length of the ` sig=<hex>` field a signature adds to a line
+	const SIGNATURE_LEN: usize = 5 + 64;
+
+	/// Creates a key from secret bytes
+	pub fn new(key: &[u8]) -> SigningKey {
+		SigningKey(key.to_vec())
+	}
+
+	fn mac(&self, line: &str) -> hmac::Hmac<sha2::Sha256> {
+		use hmac::{KeyInit, Mac};
+		let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(&self.0)
+			.expect("HMAC accepts keys of any length");
+		mac.update(line.as_bytes());
+		mac
+	}
+
+	/// Appends the signature of `line` to it as a trailing `sig=` field
+	fn sign(&self, line: &mut String) {
+		use hmac::Mac;
+		let signature = self.mac(line).finalize().into_bytes();
+		line.push_str(" sig=");
+		for byte in signature {
+			line.push_str(&format!("{:02x}", byte));
+		}
+	}
+
+	/// Returns the line and its signature, if it ends in a `sig=` field
+	fn split_signature(line: &str) -> Option<(&str, &str)> {
+		line.rsplit_once(" sig=").filter(|(_, signature)| {
+			signature.len() == SigningKey::SIGNATURE_LEN - 5
+				&& signature.bytes().all(|byte| byte.is_ascii_hexdigit())
+		})
+	}
+
+	/// Returns whether `line` ends with a valid signature
+	fn verify(&self, line: &str) -> bool {
+		use hmac::Mac;
+		let (line, signature) = match SigningKey::split_signature(line) {
+			Some(signed) => signed,
+			None => return false,
+		};
+		let decoded: Option<Vec<u8>> = (0..signature.len())
+			.step_by(2)
+			.map(|i| u8::from_str_radix(&signature[i..i + 2], 16).ok())
+			.collect();
+		match decoded {
+			Some(signature) => self.mac(line).verify_slice(&signature).is_ok(),
+			None => false,
+		}
+	}
+}
+
+#[cfg(feature = "signing")]
+impl fmt::Debug for SigningKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("SigningKey(..)")
+	}
+}
+
+/// Secret AES-256-GCM key for encrypting log files, set with
+/// [`Minilog::set_encryption_key`]. Its `Debug` output leaves the key out
+#[cfg(feature = "encryption")]
+#[derive(Clone)]
+pub struct EncryptionKey([u8; 32]);
+
+#[cfg(feature = "encryption")]
+impl EncryptionKey {
+	/// Creates a key from 32 secret bytes
+	pub fn new(key: [u8; 32]) -> EncryptionKey {
+		EncryptionKey(key)
+	}
+
+	fn cipher(&self) -> aes_gcm::Aes256Gcm {
+		use aes_gcm::KeyInit;
+		aes_gcm::Aes256Gcm::new(&self.0.into())
+	}
+
+	/// Encrypts `bytes` as a block: the length of the ciphertext as a
+	/// big-endian `u32`, a random nonce, then the ciphertext with its tag
+	fn seal(&self, bytes: &[u8]) -> Vec<u8> {
+		use aes_gcm::aead::{Aead, Generate};
+		let nonce = aes_gcm::aead::Nonce::<aes_gcm::Aes256Gcm>::generate();
+		let ciphertext = self
+			.cipher()
+			.encrypt(&nonce, bytes)
+			.expect("AES-GCM encrypts records of any length");
+		let mut block = (ciphertext.len() as u32).to_be_bytes().to_vec();
+		block.extend_from_slice(&nonce);
+		block.extend(ciphertext);
+		block
+	}
+}
+
+#[cfg(feature = "encryption")]
+impl fmt::Debug for EncryptionKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("EncryptionKey(..)")
+	}
+}
+
+/// Handle logging through the global logger with fields of its own, which
+/// the `{context}` token renders ahead of the context provider's. Created
+/// with [`Minilog::child`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinilogChild {
+	fields: Vec<(String, String)>,
+}
+
+impl MinilogChild {
+	///Creates a child of this child, carrying its fields followed by
+	/// `fields`
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// let storage = Minilog::child(&[("component", "storage")]);
+	/// let compaction = storage.child(&[("task", "compaction")]);
+	/// ```
+	pub fn child(&self, fields: &[(&str, &str)]) -> MinilogChild {
+		let mut child = self.clone();
+		child.fields.extend(fields.iter().map(|&(key, value)| (key.to_owned(), value.to_owned())));
+		child
+	}
+
+	///Logs `msg` at `level` with this child's fields
+	#[track_caller]
+	pub fn log(&self, level: Level, msg: &str) {
+		let parent = CHILD_FIELDS.with(|fields| fields.replace(self.fields.clone()));
+		log_from_caller(level, format_args!("{}", msg));
+		CHILD_FIELDS.with(|fields| *fields.borrow_mut() = parent);
+	}
+}
+
+/// Kind of a metric logged with [`Minilog::log_metric`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+	/// A running total, such as requests served
+	Counter,
+	/// A value that goes up and down, such as queue length
+	Gauge,
+}
+
+impl MetricKind {
+	fn name(self) -> &'static str {
+		match self {
+			MetricKind::Counter => "counter",
+			MetricKind::Gauge => "gauge",
+		}
+	}
+}
+
+/// Character encoding of written lines, set with [`Minilog::set_encoding`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+	/// UTF-8, the default
+	Utf8,
+	/// Little-endian UTF-16 without a byte order mark
+	Utf16Le,
+}
+
+impl Encoding {
+	fn encode(self, text: &str) -> Vec<u8> {
+		match self {
+			Encoding::Utf8 => text.as_bytes().to_vec(),
+			Encoding::Utf16Le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+		}
+	}
+}
+
+/// Throughput measured by [`Minilog::benchmark`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThroughputReport {
+	/// Number of records logged
+	pub records: u64,
+	/// Number of bytes written for those records
+	pub bytes: u64,
+	/// Time it took to log them
+	pub elapsed: Duration,
+	/// Records logged per second
+	pub records_per_sec: f64,
+	/// Bytes written per second
+	pub bytes_per_sec: f64,
+}
+
+impl Minilog {
+	/// Initializes the logger, must be called before attempting
+	/// to write log messages
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::LevelFilter;
+	/// # use minilog::Minilog;
+	/// Minilog::init(LevelFilter::Info, "logs.txt", "{level} - {msg}");
+	/// ```
+	///
+	/// Text between `{?field}` and `{/field}` is left out when the record
+	/// doesn't have that field
+	///
+	/// ```
+	/// # use log::{info, Level, LevelFilter, Record};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(
+	///     LevelFilter::Info,
+	///     "minilog_conditional_test.txt",
+	///     "{level} {msg}{?file} ({file}:{line}){/file}",
+	/// );
+	/// log::logger().log(
+	///     &Record::builder()
+	///         .level(Level::Info)
+	///         .args(format_args!("no location"))
+	///         .build(),
+	/// );
+	/// log::logger().log(
+	///     &Record::builder()
+	///         .level(Level::Info)
+	///         .file(Some("src/main.rs"))
+	///         .line(Some(7))
+	///         .args(format_args!("with location"))
+	///         .build(),
+	/// );
+	/// let file_contents =
+	///     fs::read_to_string("minilog_conditional_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_conditional_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "INFO no location\nINFO with location (src/main.rs:7)\n"
+	/// );
+	/// ```
+	///
+	/// Adding `:json` to a token, as in `{msg:json}`, escapes its value so
+	/// it can be placed inside a JSON string
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(
+	///     LevelFilter::Info,
+	///     "minilog_json_modifier_test.txt",
+	///     r#"{"level":"{level}","msg":"{msg:json}"}"#,
+	/// );
+	/// info!(r#"user said "hi""#);
+	/// let file_contents =
+	///     fs::read_to_string("minilog_json_modifier_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_json_modifier_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "{\"level\":\"INFO\",\"msg\":\"user said \\\"hi\\\"\"}\n");
+	/// ```
+	///
+	/// `{offset}` renders the byte position in the file where the line
+	/// starts, for indexing tools that seek straight to a line. Records are
+	/// written one at a time while the format string has it, so that
+	/// offsets stay exact when several threads log at once
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_offset_test.txt", "{offset} {msg}");
+	/// info!("first");
+	/// info!("second");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_offset_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_offset_test.txt").expect("Unable to delete test file.");
+	/// let lines: Vec<&str> = file_contents.split_inclusive('\n').collect();
+	/// assert_eq!(lines, ["0 first\n", "8 second\n"]);
+	/// assert_eq!(lines[1].split(' ').next(), Some(lines[0].len().to_string().as_str()));
+	/// ```
+	pub fn init(
+		loglevel: LevelFilter,
+		logfile_name: &str,
+		fmt_string: &str,
+	) -> Result<(), InitError> {
+		let mut target = Target::new(logfile_name);
+		let open_error = match &target {
+			Target::File(path) if FALLBACK_TO_STDERR.load(Ordering::Relaxed) => {
+				OpenOptions::new().append(true).create(true).open(path).err()
+			}
+			_ => None,
+		};
+		if open_error.is_some() {
+			target = Target::Stderr;
+		}
+		Minilog {
+			template: Template::parse(fmt_string),
+		}
+		.install(loglevel, target)?;
+		if let Some(e) = open_error {
+			logger().log(
+				&Record::builder()
+					.level(Level::Warn)
+					.target(module_path!())
+					.args(format_args!(
+						"Failed to open logfile {} ({}), logging to stderr instead",
+						logfile_name, e
+					))
+					.build(),
+			);
+		}
+		Ok(())
+	}
+	///Makes `init` log to stderr if it can't open the log file, e.g.
+	/// because its directory is unwritable or the disk is full, rather than
+	/// panicking on the first record. The first record logged is then a
+	/// warning saying why. Off by default
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::LevelFilter;
+	/// # use minilog::Minilog;
+	/// Minilog::set_fallback_to_stderr_on_init_failure(true);
+	/// Minilog::init(LevelFilter::Info, "/nonexistent/minilog.txt", "{level} - {msg}")
+	///     .expect("Was unable to initialize.");
+	/// log::info!("still logged, to stderr");
+	/// ```
+	pub fn set_fallback_to_stderr_on_init_failure(fallback: bool) {
+		FALLBACK_TO_STDERR.store(fallback, Ordering::Relaxed);
+	}
+	///Initializes a logger writing to a ring buffer in the POSIX shared
+	/// memory segment `name`, `size` bytes large, for a collector process to
+	/// read with `open_shmem`. The segment is created, or emptied if it
+	/// already exists. Once the ring is full, new records overwrite the
+	/// oldest ones, and records that don't fit at all are dropped. Remove
+	/// the segment with `unlink_shmem` when it's no longer needed
+	///
+	/// # Errors
+	/// Fails with `TargetUnavailable` if the segment can't be created
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, warn, LevelFilter};
+	/// # use minilog::Minilog;
+	/// Minilog::init_shmem("minilog_shmem_test", 256, LevelFilter::Info, "{level}: {msg}")
+	///     .expect("Was unable to initialize.");
+	/// let mut reader =
+	///     Minilog::open_shmem("minilog_shmem_test").expect("Was unable to open segment.");
+	/// info!("first");
+	/// warn!("second");
+	/// assert_eq!(reader.by_ref().collect::<Vec<_>>(), ["INFO: first", "WARN: second"]);
+	/// // Wrap around the end of the ring a few times
+	/// for i in 0..50 {
+	///     info!("record {}", i);
+	///     assert_eq!(reader.next(), Some(format!("INFO: record {}", i)));
+	/// }
+	/// assert_eq!(reader.next(), None);
+	/// # Minilog::unlink_shmem("minilog_shmem_test").expect("Unable to remove test segment.");
+	/// ```
+	///
+	/// ```
+	/// # use log::LevelFilter;
+	/// # use minilog::{InitError, Minilog};
+	/// // Too small to hold the ring's header
+	/// let result = Minilog::init_shmem("minilog_shmem_small_test", 8, LevelFilter::Info, "{msg}");
+	/// assert_eq!(result, Err(InitError::TargetUnavailable(std::io::ErrorKind::InvalidInput)));
+	/// ```
+	#[cfg(unix)]
+	pub fn init_shmem(
+		name: &str,
+		size: usize,
+		loglevel: LevelFilter,
+		fmt_string: &str,
+	) -> Result<(), InitError> {
+		let ring = shmem::Ring::create(name, size)
+			.map_err(|e| InitError::TargetUnavailable(e.kind()))?;
+		Minilog {
+			template: Template::parse(fmt_string),
+		}
+		.install(loglevel, Target::Shmem(ring))
+	}
+	///Opens a shared memory segment written by `init_shmem`, in this or
+	/// another process, to read the records logged to it. Reading starts
+	/// at the oldest record still in the ring
+	///
+	/// # Errors
+	/// Fails if the segment doesn't exist or isn't a Minilog ring
+	#[cfg(unix)]
+	pub fn open_shmem(name: &str) -> io::Result<ShmemReader> {
+		let ring = shmem::Ring::open(name)?;
+		Ok(ShmemReader {
+			position: ring.oldest(),
+			ring,
+			lost: 0,
+		})
+	}
+	///Removes a shared memory segment created by `init_shmem`. Processes
+	/// that still have it open can keep using it
+	///
+	/// # Errors
+	/// Fails if the segment doesn't exist
+	#[cfg(unix)]
+	pub fn unlink_shmem(name: &str) -> io::Result<()> {
+		shmem::Ring::unlink(name)
+	}
+	///Initializes a logger sending records to the systemd journal through
+	/// its native protocol, as entries with `PRIORITY`, `MESSAGE`, `TARGET`
+	/// and `CODE_*` fields. Log levels map to the syslog priorities err,
+	/// warning, info and debug, with Trace as debug too. The message is the
+	/// `{msg}` format string, which a formatter can replace
+	///
+	/// # Panics
+	/// Panics if it can't create a socket
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, warn, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::os::unix::net::UnixDatagram;
+	/// let dir = std::env::temp_dir().join(format!("minilog_journald_{}", std::process::id()));
+	/// fs::create_dir_all(&dir).expect("Was unable to create directory.");
+	/// let journal =
+	///     UnixDatagram::bind(dir.join("socket")).expect("Was unable to bind socket.");
+	/// Minilog::set_journald_socket(dir.join("socket"));
+	/// Minilog::init_journald(LevelFilter::Info);
+	/// warn!("disk low");
+	/// info!("first\nsecond");
+	/// let mut buf = [0; 4096];
+	/// let len = journal.recv(&mut buf).expect("Was unable to receive entry.");
+	/// let entry = String::from_utf8_lossy(&buf[..len]).into_owned();
+	/// let len = journal.recv(&mut buf).expect("Was unable to receive entry.");
+	/// let multiline = buf[..len].to_vec();
+	/// # fs::remove_dir_all(&dir).expect("Unable to delete test directory.");
+	/// assert!(entry.starts_with("PRIORITY=4\nMESSAGE=disk low\nTARGET="));
+	/// assert!(entry.contains("\nCODE_LINE="));
+	/// let message = b"PRIORITY=6\nMESSAGE\n\x0c\0\0\0\0\0\0\0first\nsecond\n";
+	/// assert!(multiline.starts_with(message));
+	/// ```
+	#[cfg(target_os = "linux")]
+	pub fn init_journald(loglevel: LevelFilter) -> Result<(), InitError> {
+		let socket = match std::os::unix::net::UnixDatagram::unbound() {
+			Ok(socket) => socket,
+			Err(e) => panic!("{}: Failed to create journald socket", e),
+		};
+		let path = lock(&JOURNALD_SOCKET)
+			.clone()
+			.unwrap_or_else(|| PathBuf::from("/run/systemd/journal/socket"));
+		Minilog {
+			template: Template::parse("{msg}"),
+		}
+		.install(loglevel, Target::Journald(socket, path))
+	}
+	///Sends journal entries to the socket at `path` instead of journald's
+	/// own, e.g. to check entries in tests. Takes effect for loggers
+	/// initialized afterwards with `init_journald`
+	#[cfg(target_os = "linux")]
+	pub fn set_journald_socket<P: AsRef<Path>>(path: P) {
+		*lock(&JOURNALD_SOCKET) = Some(path.as_ref().to_path_buf());
+	}
+	///Initializes a logger writing each record to the file whose path is
+	/// `path_template` rendered for that record, using the same tokens as
+	/// format strings, e.g. `logs/{category}.log` for a file per category.
+	/// Missing directories are created. Files are kept open for speed, but
+	/// only up to `set_max_open_files` of them (32 by default), so that a
+	/// template rendering many paths can't run out of file descriptors;
+	/// beyond that, the least recently used file is closed. Lines that don't
+	/// come from a record, like sticky lines, go to every open file. Path
+	/// separators, `..` and NUL in token values are replaced with
+	/// underscores, so that logged data can't pick a file elsewhere
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init_file_template(
+	///     LevelFilter::Info,
+	///     "minilog_file_template_test/{category}.log",
+	///     "{level} - {msg}",
+	/// );
+	/// Minilog::log_cat(Level::Info, "tenant-a", "signed up");
+	/// Minilog::log_cat(Level::Warn, "tenant-b", "over quota");
+	/// Minilog::log_cat(Level::Info, "tenant-a", "upgraded");
+	/// let tenant_a = fs::read_to_string("minilog_file_template_test/tenant-a.log")
+	///     .expect("Was unable to read file.");
+	/// let tenant_b = fs::read_to_string("minilog_file_template_test/tenant-b.log")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_dir_all("minilog_file_template_test").expect("Unable to delete test files.");
+	/// assert_eq!(tenant_a, "INFO - signed up\nINFO - upgraded\n");
+	/// assert_eq!(tenant_b, "WARN - over quota\n");
+	/// ```
+	///
+	/// Paths may use the `{offset}` token as well, which counts the bytes
+	/// written to all the files
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init_file_template(
+	///     LevelFilter::Info,
+	///     "minilog_file_template_offset_test/{offset}.log",
+	///     "{msg}",
+	/// );
+	/// info!("first");
+	/// info!("second");
+	/// let first = fs::read_to_string("minilog_file_template_offset_test/0.log")
+	///     .expect("Was unable to read file.");
+	/// let second = fs::read_to_string("minilog_file_template_offset_test/6.log")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_dir_all("minilog_file_template_offset_test")
+	/// #     .expect("Unable to delete test files.");
+	/// assert_eq!((first.as_str(), second.as_str()), ("first\n", "second\n"));
+	/// ```
+	///
+	/// ```
+	/// # use log::{Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::path::Path;
+	/// Minilog::init_file_template(
+	///     LevelFilter::Info,
+	///     "minilog_file_template_escape_test/tenants/{category}.log",
+	///     "{msg}",
+	/// );
+	/// Minilog::log_cat(Level::Info, "../escaped", "hello");
+	/// let escaped = Path::new("minilog_file_template_escape_test/escaped.log").exists();
+	/// let contents = fs::read_to_string("minilog_file_template_escape_test/tenants/___escaped.log")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_dir_all("minilog_file_template_escape_test")
+	/// #     .expect("Unable to delete test files.");
+	/// assert!(!escaped);
+	/// assert_eq!(contents, "hello\n");
+	/// ```
+	pub fn init_file_template(
+		loglevel: LevelFilter,
+		path_template: &str,
+		fmt_string: &str,
+	) -> Result<(), InitError> {
+		let files = Mutex::new(OpenFiles(Vec::new()));
+		Minilog {
+			template: Template::parse(fmt_string),
+		}
+		.install(loglevel, Target::FileTemplate(Template::parse(path_template), files))
+	}
+	///Sets how many files a logger from `init_file_template` keeps open at
+	/// once
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// Minilog::set_max_open_files(8);
+	/// ```
+	pub fn set_max_open_files(max_open: usize) {
+		MAX_OPEN_FILES.store(max_open, Ordering::Relaxed);
+	}
+	///Initializes a logger with default settings
+	///
+	/// # Examples
+	/// ```
+	/// # use log::LevelFilter;
+	/// # use minilog::Minilog;
+	/// Minilog::init_default();
+	/// ```
+	pub fn init_default() -> Result<(), InitError> {
+		Minilog {
+			template: Template::parse(DEFAULT_FORMAT),
+		}
+		.install(LevelFilter::Trace, Target::new("logs.txt"))
+	}
+	///Sets the maximum level of log message to write
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::LevelFilter;
+	/// # use minilog::Minilog;
+	/// Minilog::set_log_level(LevelFilter::Info);
+	/// ```
+	pub fn set_log_level(loglevel: LevelFilter) {
+		match lock(&LEVEL_OVERRIDE).as_mut() {
+			Some(current) => {
+				current.restore = loglevel;
+				set_max_level(loglevel.max(current.level));
+			}
+			None => set_max_level(loglevel),
+		}
+	}
+	///Logs or panics if loglevel is too low
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::Level;
+	/// # use log::LevelFilter;
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_output_test.txt", "{level} - {msg}");
+	/// Minilog::log_or_panic(Level::Error, "Error!");
+	/// # fs::remove_file("minilog_output_test.txt").expect("Unable to delete test file.");
+	/// ```
+	///
+	/// ```should_panic
+	/// # use log::Level;
+	/// # use log::LevelFilter;
+	/// # use minilog::Minilog;
+	/// //should panic
+	/// Minilog::init(LevelFilter::Info, "minilog_output_test.txt", "{level} - {msg}");
+	/// Minilog::log_or_panic(Level::Trace, "Trace!");
+	/// ```
+	///
+	#[track_caller]
+	pub fn log_or_panic(loglevel: Level, msg: &str) {
+		if loglevel > max_level() {
+			panic!("{} is too low to log", loglevel);
+		}
+		log_from_caller(loglevel, format_args!("{}", msg));
+	}
+	///logs a message, upgrading the log level if log level isn't high enough
+	/// ```
+	/// # use log::{Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_output_test_upgrade.txt", "{level} - {msg}");
+	/// Minilog::log_upgrade(Level::Trace, "Trace!");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_output_test_upgrade.txt").expect("Was unable to read file.");
+	///# fs::remove_file("minilog_output_test_upgrade.txt").expect("Unable to delete test file.");
+	///assert_eq!(
+	///     file_contents,
+	///     "TRACE - Trace!\n"
+	///);
+	/// ```
+	#[track_caller]
+	pub fn log_upgrade(loglevel: Level, msg: &str) {
+		if loglevel > max_level() {
+			set_max_level(loglevel.to_level_filter())
+		}
+		log_from_caller(loglevel, format_args!("{}", msg));
+	}
+	///logs a message, temporarily upgrading loglevel if it isn't high enough
+	/// ```
+    /// # use log::{Level, LevelFilter, trace};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_output_test_temp.txt", "{level} - {msg}");
+	/// Minilog::log_upgrade(Level::Trace, "Trace!");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_output_test_temp.txt").expect("Was unable to read file.");
+	///# fs::remove_file("minilog_output_test_temp.txt").expect("Unable to delete test file.");
+	///assert_eq!(
+	///     file_contents,
+	///     "TRACE - Trace!\n"
+	///);
+	/// ```
+	#[track_caller]
+	pub fn log_upgrade_temp(loglevel: Level, msg: &str) {
+		if loglevel > max_level() {
+			let current_level = max_level(); 
+			Minilog::log_upgrade(loglevel, msg);
+			set_max_level(current_level);
+		}
+	}
+    ///returns option with the maximum log level, none if logging is off
+    ///```
+    ///# use log::{Level, LevelFilter, trace};
+    ///# use minilog::Minilog;
+    ///Minilog::init(LevelFilter::Trace, "minilog_output_test.txt", "{level} - {msg}");
+    ///assert_eq!(Minilog::log_level(), Some(Level::Trace));
+    ///```
+    pub fn log_level() -> Option<Level> {
+        max_level().to_level()
+    }
+	///Drops all records coming from the given source file. A bare file
+	/// name such as `foo.rs` matches any path ending in it
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{Level, LevelFilter, Record};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_mute_test.txt", "{level} - {msg}");
+	/// Minilog::mute_file("chatty.rs");
+	/// let log_from = |file, msg| {
+	///     log::logger().log(
+	///         &Record::builder()
+	///             .level(Level::Info)
+	///             .file(Some(file))
+	///             .args(format_args!("{}", msg))
+	///             .build(),
+	///     )
+	/// };
+	/// log_from("vendor/chatty.rs", "muted");
+	/// log_from("src/main.rs", "kept");
+	/// Minilog::unmute_file("chatty.rs");
+	/// log_from("vendor/chatty.rs", "unmuted");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_mute_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_mute_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "INFO - kept\nINFO - unmuted\n");
+	/// ```
+	pub fn mute_file(file: &str) {
+		let mut muted = lock(&MUTED_FILES);
+		if !muted.iter().any(|f| f == file) {
+			muted.push(file.to_owned());
+		}
+	}
+	///Stops dropping records from a file previously passed to `mute_file`
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// Minilog::mute_file("chatty.rs");
+	/// Minilog::unmute_file("chatty.rs");
+	/// ```
+	pub fn unmute_file(file: &str) {
+		lock(&MUTED_FILES).retain(|f| f != file);
+	}
+	///Caps the length of each fully formatted line, including everything
+	/// the format string adds around the message, at `max_bytes`.
+	/// Lines are cut on a char boundary; `None` removes the limit
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{warn, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_line_bytes_test.txt", "[service-a] {level} - {msg}");
+	/// Minilog::set_max_line_bytes(Some(24));
+	/// Minilog::set_truncation_indicator("...");
+	/// warn!("disk usage is above ninety percent");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_line_bytes_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_line_bytes_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "[service-a] WARN - di...\n");
+	/// ```
+	pub fn set_max_line_bytes(max_bytes: Option<usize>) {
+		MAX_LINE_BYTES.store(max_bytes.unwrap_or(usize::MAX), Ordering::Relaxed);
+	}
+	///Sets the text that ends a line shortened by `set_max_line_bytes`.
+	/// The indicator counts towards the limit, and is empty by default
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// Minilog::set_truncation_indicator(" [truncated]");
+	/// ```
+	pub fn set_truncation_indicator(indicator: &str) {
+		*lock(&TRUNCATION_INDICATOR) = indicator.to_owned();
+	}
+	///Sets a [`Formatter`] that formats every record instead of the
+	/// format string passed to `init`. `None` goes back to the format string
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// Minilog::set_formatter(None);
+	/// ```
+	pub fn set_formatter(formatter: Option<Box<dyn Formatter + Send + Sync>>) {
+		*FORMATTER.write().unwrap_or_else(|e| e.into_inner()) = formatter;
+	}
+	///Formats records whose target is a key of `formats` with the format
+	/// string it maps to, instead of the one passed to `init`, e.g. to give
+	/// audit records a more detailed layout. Replaces any earlier mapping
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::collections::BTreeMap;
+	/// # use std::fs;
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// Minilog::init(LevelFilter::Info, "minilog_target_formats_test.txt", "{level} - {msg}");
+	/// let mut formats = BTreeMap::new();
+	/// formats.insert("audit", "AUDIT {timestamp} {level}: {msg}");
+	/// Minilog::set_target_formats(formats);
+	/// Minilog::set_clock(Box::new(|| UNIX_EPOCH + Duration::from_secs(1_792_011_600)));
+	/// info!(target: "audit", "user 7 granted admin");
+	/// info!("cache warmed");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_target_formats_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_target_formats_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "AUDIT 2026-10-14T21:00:00.000Z INFO: user 7 granted admin\nINFO - cache warmed\n"
+	/// );
+	/// ```
+	pub fn set_target_formats(formats: BTreeMap<&str, &str>) {
+		*TARGET_FORMATS.write().unwrap_or_else(|e| e.into_inner()) = formats
+			.into_iter()
+			.map(|(target, fmt)| (target.to_owned(), Template::parse(fmt)))
+			.collect();
+	}
+	///Renders the fields returned by `provider` as `key=value` pairs with
+	/// the `{context}` token, for context that comes from elsewhere in the
+	/// program, such as the tenant of the current request. The provider is
+	/// called on the logging thread every time the token is rendered, so it
+	/// adds its own cost to every record and should be quick
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::atomic::{AtomicU64, Ordering};
+	/// static TENANT: AtomicU64 = AtomicU64::new(7);
+	/// Minilog::init(LevelFilter::Info, "minilog_context_test.txt", "{msg} {context}");
+	/// Minilog::set_context_provider(Box::new(|| {
+	///     vec![("tenant".to_owned(), TENANT.load(Ordering::SeqCst).to_string())]
+	/// }));
+	/// info!("request");
+	/// TENANT.store(8, Ordering::SeqCst);
+	/// info!("request");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_context_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_context_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "request tenant=7\nrequest tenant=8\n");
+	/// ```
+	pub fn set_context_provider(provider: Box<dyn Fn() -> Vec<(String, String)> + Send + Sync>) {
+		*CONTEXT_PROVIDER.write().unwrap_or_else(|e| e.into_inner()) = Some(provider);
+	}
+	///Creates a child logger carrying `fields`, e.g. the name of a library
+	/// component. It logs through the global logger, with its fields
+	/// rendered by the `{context}` token
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_child_test.txt", "{level} {context}: {msg}");
+	/// let storage = Minilog::child(&[("component", "storage")]);
+	/// let network = Minilog::child(&[("component", "network"), ("peer", "10.0.0.2")]);
+	/// storage.log(Level::Info, "compacted");
+	/// network.log(Level::Warn, "connection reset");
+	/// info!("from the parent");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_child_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_child_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "INFO component=storage: compacted\n\
+	///      WARN component=network peer=10.0.0.2: connection reset\n\
+	///      INFO : from the parent\n"
+	/// );
+	/// ```
+	pub fn child(fields: &[(&str, &str)]) -> MinilogChild {
+		MinilogChild { fields: Vec::new() }.child(fields)
+	}
+	///Runs `call` with records from logging helpers attributed to
+	/// `module`. Used by the `here!` macro
+	#[doc(hidden)]
+	pub fn in_module<R>(module: &'static str, call: impl FnOnce() -> R) -> R {
+		let outer = CALLER_MODULE.with(|current| current.replace(Some(module)));
+		let result = call();
+		CALLER_MODULE.with(|current| current.set(outer));
+		result
+	}
+	///Sets the value of the `{build_id}` token, typically a git commit
+	/// hash passed in by a build script
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_build_id_test.txt", "[{build_id}] {msg}");
+	/// Minilog::set_build_id("3f9c2ab");
+	/// info!("started");
+	/// info!("stopped");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_build_id_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_build_id_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "[3f9c2ab] started\n[3f9c2ab] stopped\n");
+	/// ```
+	pub fn set_build_id(build_id: &str) {
+		*BUILD_ID.write().unwrap_or_else(|e| e.into_inner()) = build_id.to_owned();
+	}
+	///Lists the tokens format strings can use, with a short description of
+	/// each, e.g. for `--help` output
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// for (token, description) in Minilog::available_tokens() {
+	///     println!("{{{}}}: {}", token, description);
+	/// }
+	/// assert!(Minilog::available_tokens().iter().any(|&(token, _)| token == "msg"));
+	/// ```
+	pub fn available_tokens() -> &'static [(&'static str, &'static str)] {
+		TOKENS
+	}
+	///Drops the spaces around tokens and conditional sections that render
+	/// empty, so that a format like `{worker} {level} {msg}` doesn't leave a
+	/// double or leading space when there's no worker label. Only the
+	/// format string's own spaces are dropped; messages and other token
+	/// values are written exactly as they are
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// let fmt = "{level} {operation} {worker} {msg}";
+	/// Minilog::init(LevelFilter::Info, "minilog_collapse_test.txt", fmt);
+	/// Minilog::set_collapse_whitespace(true);
+	/// Minilog::set_worker_label("indexer-1");
+	/// info!("spaced  out");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_collapse_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_collapse_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "INFO indexer-1 spaced  out\n");
+	/// ```
+	pub fn set_collapse_whitespace(collapse: bool) {
+		COLLAPSE_WHITESPACE.store(collapse, Ordering::Relaxed);
+	}
+	///Shortens messages so that each line fits the width of the terminal
+	/// that stdout or stderr is logging to. The width is looked up for
+	/// every record, so resizing the terminal takes effect right away.
+	/// When it can't be determined, e.g. when logging to a file, 80 columns
+	/// are assumed
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_auto_width_test.txt", "{level} | {msg}");
+	/// Minilog::set_auto_width(true);
+	/// Minilog::set_width_source(Box::new(|| Some(80)));
+	/// info!("{}", "x".repeat(100));
+	/// info!("short");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_auto_width_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_auto_width_test.txt").expect("Unable to delete test file.");
+	/// let lines: Vec<&str> = file_contents.lines().collect();
+	/// assert_eq!(lines[0], format!("INFO | {}", "x".repeat(73)));
+	/// assert_eq!(lines[1], "INFO | short");
+	/// ```
+	pub fn set_auto_width(auto_width: bool) {
+		AUTO_WIDTH.store(auto_width, Ordering::Relaxed);
+	}
+	///Replaces the terminal width lookup used by auto width with `source`.
+	/// Returning `None` falls back to 80 columns
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// Minilog::set_width_source(Box::new(|| std::env::var("COLUMNS").ok()?.parse().ok()));
+	/// ```
+	pub fn set_width_source(source: Box<dyn Fn() -> Option<usize> + Send + Sync>) {
+		*WIDTH_SOURCE.write().unwrap_or_else(|e| e.into_inner()) = Some(source);
+	}
+	///Only renders the `{timestamp}` token for records at least as severe
+	/// as `levels`; for other records it renders as an empty string, which
+	/// also leaves out any `{?timestamp}` section. Timestamps are RFC 3339
+	/// in UTC, and are rendered for all levels by default
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{error, info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// Minilog::set_clock(Box::new(|| UNIX_EPOCH + Duration::from_secs(1_792_011_600)));
+	/// Minilog::init(
+	///     LevelFilter::Info,
+	///     "minilog_timestamp_levels_test.txt",
+	///     "{?timestamp}{timestamp} {/timestamp}{level} - {msg}",
+	/// );
+	/// Minilog::set_timestamp_levels(LevelFilter::Warn);
+	/// info!("terse");
+	/// error!("timestamped");
+	/// let file_contents = fs::read_to_string("minilog_timestamp_levels_test.txt")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_timestamp_levels_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "INFO - terse\n2026-10-14T21:00:00.000Z ERROR - timestamped\n"
+	/// );
+	/// ```
+	pub fn set_timestamp_levels(levels: LevelFilter) {
+		*TIMESTAMP_LEVELS.write().unwrap_or_else(|e| e.into_inner()) = levels;
+	}
+	///Sets the letter case of the `{level}` token, which is upper case by
+	/// default
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{LevelCase, Minilog};
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_level_case_test.txt", "{level} - {msg}");
+	/// info!("upper");
+	/// Minilog::set_level_case(LevelCase::Lower);
+	/// info!("lower");
+	/// Minilog::set_level_case(LevelCase::Title);
+	/// info!("title");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_level_case_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_level_case_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "INFO - upper\ninfo - lower\nInfo - title\n");
+	/// ```
+	pub fn set_level_case(case: LevelCase) {
+		*LEVEL_CASE.write().unwrap_or_else(|e| e.into_inner()) = case;
+	}
+	///Sets what gets logged for a record whose formatting panics, in a
+	/// [`Formatter`] or a context provider. The panic is caught either way,
+	/// so a bug in custom formatting can't corrupt a line or take down the
+	/// thread logging it
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{Minilog, OnFormatError};
+	/// # use std::fs;
+	/// # use std::sync::atomic::{AtomicBool, Ordering};
+	/// static BROKEN: AtomicBool = AtomicBool::new(false);
+	/// Minilog::init(LevelFilter::Info, "minilog_format_error_test.txt", "{msg} {context}");
+	/// Minilog::set_context_provider(Box::new(|| {
+	///     assert!(!BROKEN.load(Ordering::SeqCst), "context lookup failed");
+	///     vec![("tenant".to_owned(), "7".to_owned())]
+	/// }));
+	/// info!("working");
+	/// BROKEN.store(true, Ordering::SeqCst);
+	/// info!("raw");
+	/// Minilog::set_on_format_error(OnFormatError::Skip);
+	/// info!("skipped");
+	/// Minilog::set_on_format_error(OnFormatError::Placeholder("<unformattable>".to_owned()));
+	/// info!("replaced");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_format_error_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_format_error_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "working tenant=7\nraw\n<unformattable>\n");
+	/// ```
+	pub fn set_on_format_error(policy: OnFormatError) {
+		*ON_FORMAT_ERROR.write().unwrap_or_else(|e| e.into_inner()) = policy;
+	}
+	///Signs every line with an HMAC-SHA256 of it under `key`, appended as a
+	/// trailing ` sig=<hex>` field, so that lines forged or altered by
+	/// someone without the key can be found with `verify_signatures`. Sticky
+	/// lines and the shutdown summary are signed like records. The
+	/// field's 69 bytes count towards `set_max_line_bytes`, so lines are
+	/// shortened further to make room for it. `None` stops signing.
+	/// Requires the `signing` feature
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{Minilog, SigningKey};
+	/// # use std::fs;
+	/// let key = SigningKey::new(b"audit secret");
+	/// Minilog::init(LevelFilter::Info, "minilog_signing_test.txt", "{level} - {msg}");
+	/// Minilog::set_signing_key(Some(key.clone()));
+	/// info!("user 7 granted admin");
+	/// info!("user 8 granted admin");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_signing_test.txt").expect("Was unable to read file.");
+	/// assert!(file_contents.starts_with("INFO - user 7 granted admin sig="));
+	/// let bad_lines = Minilog::verify_signatures("minilog_signing_test.txt", &key)
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_signing_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(bad_lines, Vec::<usize>::new());
+	/// assert_eq!(format!("{:?}", key), "SigningKey(..)");
+	/// ```
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{Minilog, SigningKey};
+	/// # use std::fs;
+	/// let key = SigningKey::new(b"audit secret");
+	/// Minilog::init(LevelFilter::Info, "minilog_signing_capped_test.txt", "{level} - {msg}");
+	/// Minilog::set_signing_key(Some(key.clone()));
+	/// Minilog::set_max_line_bytes(Some(100));
+	/// info!("user 7 granted admin on every project in the organization");
+	/// let file_contents = fs::read_to_string("minilog_signing_capped_test.txt")
+	///     .expect("Was unable to read file.");
+	/// let bad_lines = Minilog::verify_signatures("minilog_signing_capped_test.txt", &key)
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_signing_capped_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents.len(), 100 + 1);
+	/// assert!(file_contents.starts_with("INFO - user 7 granted admin on  sig="));
+	/// assert_eq!(bad_lines, Vec::<usize>::new());
+	/// ```
+	#[cfg(feature = "signing")]
+	pub fn set_signing_key(key: Option<SigningKey>) {
+		*SIGNING_KEY.write().unwrap_or_else(|e| e.into_inner()) = key;
+	}
+	///Checks the signatures of the records in the file at `path` against
+	/// `key`, returning the numbers, starting from 1, of the first lines of
+	/// records whose signature is missing or wrong. A record spanning
+	/// several lines, like a backtrace, is signed and checked as a whole,
+	/// with its signature at the end of its last line. Requires the
+	/// `signing` feature
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{Minilog, SigningKey};
+	/// # use std::fs;
+	/// let key = SigningKey::new(b"audit secret");
+	/// Minilog::init(LevelFilter::Info, "minilog_tampered_test.txt", "{level} - {msg}");
+	/// Minilog::set_signing_key(Some(key.clone()));
+	/// info!("user 7 granted admin");
+	/// info!("user 8 granted admin");
+	/// info!("user 9 granted admin");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_tampered_test.txt").expect("Was unable to read file.");
+	/// let tampered = file_contents.replacen("user 8", "user 6", 1);
+	/// fs::write("minilog_tampered_test.txt", tampered).expect("Was unable to write file.");
+	/// let bad_lines = Minilog::verify_signatures("minilog_tampered_test.txt", &key)
+	///     .expect("Was unable to read file.");
+	/// let other_key = SigningKey::new(b"guessed secret");
+	/// let bad_with_other_key = Minilog::verify_signatures("minilog_tampered_test.txt", &other_key)
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_tampered_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(bad_lines, vec![2]);
+	/// assert_eq!(bad_with_other_key, vec![1, 2, 3]);
+	/// ```
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{Minilog, SigningKey};
+	/// # use std::fs;
+	/// let key = SigningKey::new(b"audit secret");
+	/// Minilog::init(LevelFilter::Info, "minilog_multiline_signed_test.txt", "{level} - {msg}");
+	/// Minilog::set_signing_key(Some(key.clone()));
+	/// info!("first\nsecond");
+	/// info!("third");
+	/// let good = Minilog::verify_signatures("minilog_multiline_signed_test.txt", &key)
+	///     .expect("Was unable to read file.");
+	/// let file_contents = fs::read_to_string("minilog_multiline_signed_test.txt")
+	///     .expect("Was unable to read file.");
+	/// let tampered = file_contents.replacen("second", "sec0nd", 1);
+	/// fs::write("minilog_multiline_signed_test.txt", tampered).expect("Was unable to write file.");
+	/// let bad = Minilog::verify_signatures("minilog_multiline_signed_test.txt", &key)
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_multiline_signed_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(good, Vec::<usize>::new());
+	/// assert_eq!(bad, vec![1]);
+	/// ```
+	///
+	/// Sticky lines and the shutdown summary are signed too
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{Minilog, SigningKey};
+	/// # use std::fs;
+	/// let key = SigningKey::new(b"audit secret");
+	/// Minilog::init(LevelFilter::Info, "minilog_signed_extras_test.txt", "{level} - {msg}");
+	/// Minilog::set_signing_key(Some(key.clone()));
+	/// Minilog::set_emit_summary_on_shutdown(true);
+	/// Minilog::add_sticky_line("== session 42 ==");
+	/// info!("one");
+	/// info!("two");
+	/// Minilog::shutdown();
+	/// info!("three");
+	/// let bad = Minilog::verify_signatures("minilog_signed_extras_test.txt", &key)
+	///     .expect("Was unable to read file.");
+	/// let file_contents = fs::read_to_string("minilog_signed_extras_test.txt")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_signed_extras_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents.lines().count(), 5);
+	/// assert_eq!(bad, Vec::<usize>::new());
+	/// ```
+	#[cfg(feature = "signing")]
+	pub fn verify_signatures<P: AsRef<Path>>(path: P, key: &SigningKey) -> io::Result<Vec<usize>> {
+		let mut bad = Vec::new();
+		// First line number and text of the record read so far
+		let mut record: Option<(usize, String)> = None;
+		for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+			match &mut record {
+				Some((_, text)) => {
+					text.push('\n');
+					text.push_str(line);
+				}
+				None => record = Some((i + 1, line.to_owned())),
+			}
+			if SigningKey::split_signature(line).is_some() {
+				let (first, text) = record.take().unwrap();
+				if !key.verify(&text) {
+					bad.push(first);
+				}
+			}
+		}
+		bad.extend(record.map(|(first, _)| first));
+		Ok(bad)
+	}
+	///Encrypts everything written to the log file with AES-256-GCM under
+	/// `key`, so that sensitive lines aren't readable at rest. Each write
+	/// becomes a block with a nonce of its own and an authentication tag,
+	/// so the file stays append-only; read it back with `decrypt_log`.
+	/// `None` goes back to plain text. Requires the `encryption` feature
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{EncryptionKey, Minilog};
+	/// # use std::fs;
+	/// let key = EncryptionKey::new([7; 32]);
+	/// Minilog::init(LevelFilter::Info, "minilog_encryption_test.txt", "{level} - {msg}");
+	/// Minilog::set_encryption_key(Some(key.clone()));
+	/// info!("card ending 4242 charged");
+	/// info!("card ending 1881 refunded");
+	/// let raw = fs::read("minilog_encryption_test.txt").expect("Was unable to read file.");
+	/// let decrypted = Minilog::decrypt_log("minilog_encryption_test.txt", &key)
+	///     .expect("Was unable to decrypt file.");
+	/// # fs::remove_file("minilog_encryption_test.txt").expect("Unable to delete test file.");
+	/// assert!(!String::from_utf8_lossy(&raw).contains("4242"));
+	/// assert_eq!(decrypted, "INFO - card ending 4242 charged\nINFO - card ending 1881 refunded\n");
+	/// assert_eq!(format!("{:?}", key), "EncryptionKey(..)");
+	/// ```
+	///
+	/// It covers every file a logger writes, including those of
+	/// `init_file_template`
+	///
+	/// ```
+	/// # use log::{Level, LevelFilter};
+	/// # use minilog::{EncryptionKey, Minilog};
+	/// # use std::fs;
+	/// let key = EncryptionKey::new([7; 32]);
+	/// Minilog::init_file_template(
+	///     LevelFilter::Info,
+	///     "minilog_encryption_template_test/{category}.log",
+	///     "{msg}",
+	/// );
+	/// Minilog::set_encryption_key(Some(key.clone()));
+	/// Minilog::log_cat(Level::Info, "tenant-a", "SECRET-4242");
+	/// let path = "minilog_encryption_template_test/tenant-a.log";
+	/// let raw = fs::read(path).expect("Was unable to read file.");
+	/// let decrypted = Minilog::decrypt_log(path, &key).expect("Was unable to decrypt file.");
+	/// # fs::remove_dir_all("minilog_encryption_template_test").expect("Unable to delete test files.");
+	/// assert!(!String::from_utf8_lossy(&raw).contains("4242"));
+	/// assert_eq!(decrypted, "SECRET-4242\n");
+	/// ```
+	///
+	/// and the sticky lines `snapshot_to` starts a fresh file with
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{EncryptionKey, Minilog};
+	/// # use std::fs;
+	/// let key = EncryptionKey::new([7; 32]);
+	/// Minilog::init(LevelFilter::Info, "minilog_encryption_snapshot_test.txt", "{msg}");
+	/// Minilog::set_encryption_key(Some(key.clone()));
+	/// Minilog::add_sticky_line("session SECRET-42");
+	/// info!("before");
+	/// let snapshot = Minilog::snapshot_to("minilog_encryption_snapshot_test.1.txt")
+	///     .expect("Was unable to take a snapshot.");
+	/// info!("after");
+	/// let raw =
+	///     fs::read("minilog_encryption_snapshot_test.txt").expect("Was unable to read file.");
+	/// let active = Minilog::decrypt_log("minilog_encryption_snapshot_test.txt", &key)
+	///     .expect("Was unable to decrypt file.");
+	/// let old = Minilog::decrypt_log(&snapshot, &key).expect("Was unable to decrypt file.");
+	/// # fs::remove_file("minilog_encryption_snapshot_test.txt").expect("Unable to delete test file.");
+	/// # fs::remove_file(&snapshot).expect("Unable to delete test file.");
+	/// assert!(!String::from_utf8_lossy(&raw).contains("SECRET"));
+	/// assert_eq!(active, "session SECRET-42\nafter\n");
+	/// assert_eq!(old, "session SECRET-42\nbefore\n");
+	/// ```
+	#[cfg(feature = "encryption")]
+	pub fn set_encryption_key(key: Option<EncryptionKey>) {
+		*ENCRYPTION_KEY.write().unwrap_or_else(|e| e.into_inner()) = key;
+	}
+	///Decrypts a log file written with `set_encryption_key`, returning its
+	/// plain text. Requires the `encryption` feature
+	/// # Errors
+	/// Fails with `InvalidData` if a block was encrypted under another key,
+	/// was altered, or is cut short
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{EncryptionKey, Minilog};
+	/// # use std::fs;
+	/// # use std::io;
+	/// Minilog::init(LevelFilter::Info, "minilog_wrong_key_test.txt", "{level} - {msg}");
+	/// Minilog::set_encryption_key(Some(EncryptionKey::new([7; 32])));
+	/// info!("card ending 4242 charged");
+	/// let wrong_key = EncryptionKey::new([8; 32]);
+	/// let decrypted = Minilog::decrypt_log("minilog_wrong_key_test.txt", &wrong_key);
+	/// # fs::remove_file("minilog_wrong_key_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(decrypted.unwrap_err().kind(), io::ErrorKind::InvalidData);
+	/// ```
+	#[cfg(feature = "encryption")]
+	pub fn decrypt_log<P: AsRef<Path>>(path: P, key: &EncryptionKey) -> io::Result<String> {
+		use aes_gcm::aead::{Aead, Nonce};
+		use std::convert::TryFrom;
+		let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+		let cipher = key.cipher();
+		let mut rest = &fs::read(path)?[..];
+		let mut plain = Vec::new();
+		while !rest.is_empty() {
+			if rest.len() < 16 {
+				return Err(invalid("Encrypted block is cut short"));
+			}
+			let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+			if rest.len() - 16 < len {
+				return Err(invalid("Encrypted block is cut short"));
+			}
+			let nonce = Nonce::<aes_gcm::Aes256Gcm>::try_from(&rest[4..16]).unwrap();
+			let block = cipher
+				.decrypt(&nonce, &rest[16..16 + len])
+				.map_err(|_| invalid("Encrypted block failed authentication"))?;
+			plain.extend(block);
+			rest = &rest[16 + len..];
+		}
+		String::from_utf8(plain).map_err(|_| invalid("Decrypted log isn't UTF-8"))
+	}
+	///Only renders the `{file}` and `{line}` tokens for records at `levels`
+	/// or above, e.g. Warn to keep locations on problems but off routine
+	/// lines. Below it they render empty, like records without a location
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, warn, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// let fmt = "{level}{?line} ({file}:{line}){/line} - {msg}";
+	/// Minilog::init(LevelFilter::Info, "minilog_location_levels_test.txt", fmt);
+	/// Minilog::set_location_levels(LevelFilter::Warn);
+	/// info!("routine");
+	/// warn!("unusual");
+	/// let file_contents = fs::read_to_string("minilog_location_levels_test.txt")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_location_levels_test.txt").expect("Unable to delete test file.");
+	/// let lines: Vec<&str> = file_contents.lines().collect();
+	/// assert_eq!(lines[0], "INFO - routine");
+	/// assert!(lines[1].starts_with(&format!("WARN ({}:", file!())));
+	/// assert!(lines[1].ends_with(") - unusual"));
+	/// ```
+	pub fn set_location_levels(levels: LevelFilter) {
+		*LOCATION_LEVELS.write().unwrap_or_else(|e| e.into_inner()) = levels;
+	}
+	///Sets the delimiter rendered by the `{sep}` token, so that the
+	/// separator between fields can be changed without touching the format
+	/// string. Defaults to `" - "`
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_separator_test.txt", "{level}{sep}{msg}");
+	/// info!("default");
+	/// Minilog::set_field_separator("\t");
+	/// info!("tab separated");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_separator_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_separator_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "INFO - default\nINFO\ttab separated\n");
+	/// ```
+	pub fn set_field_separator(separator: &str) {
+		*FIELD_SEPARATOR.write().unwrap_or_else(|e| e.into_inner()) = Some(separator.to_owned());
+	}
+	///Strips trailing whitespace, including newlines, from messages
+	/// before they're formatted, so that messages already ending in a
+	/// newline don't leave blank lines behind. Off by default
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_trim_test.txt", "{level} - {msg}");
+	/// Minilog::set_trim_message(true);
+	/// info!("first\n");
+	/// info!("second  ");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_trim_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_trim_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "INFO - first\nINFO - second\n");
+	/// ```
+	pub fn set_trim_message(trim: bool) {
+		TRIM_MESSAGE.store(trim, Ordering::Relaxed);
+	}
+	///Sets the text the `{msg}` token renders for records with an empty
+	/// message, e.g. `(no message)`, so that they don't leave a bare prefix
+	/// behind. A message emptied by `set_trim_message` counts as empty.
+	/// Empty by default
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_empty_message_test.txt", "{level} - {msg}");
+	/// info!("");
+	/// Minilog::set_empty_message_text("(no message)");
+	/// info!("");
+	/// info!("not empty");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_empty_message_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_empty_message_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "INFO - \nINFO - (no message)\nINFO - not empty\n");
+	/// ```
+	pub fn set_empty_message_text(text: &str) {
+		*EMPTY_MESSAGE_TEXT.write().unwrap_or_else(|e| e.into_inner()) = text.to_owned();
+	}
+	///Replaces the system clock used for anything time dependent,
+	/// mainly so that tests can control the passage of time. Times before
+	/// the Unix epoch, e.g. from a machine without a set real-time clock,
+	/// are fine; timestamps too far off for RFC 3339 render as the epoch
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::atomic::{AtomicU64, Ordering};
+	/// # use std::sync::Arc;
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// Minilog::init(LevelFilter::Info, "minilog_clock_test.txt", "{timestamp} {msg}");
+	/// let secs_before_epoch = Arc::new(AtomicU64::new(90));
+	/// let clock_secs = secs_before_epoch.clone();
+	/// Minilog::set_clock(Box::new(move || {
+	///     UNIX_EPOCH - Duration::from_secs(clock_secs.load(Ordering::SeqCst))
+	/// }));
+	/// info!("rtc not set");
+	/// secs_before_epoch.store(100_000 * 365 * 86_400, Ordering::SeqCst);
+	/// info!("rtc garbage");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_clock_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_clock_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "1969-12-31T23:58:30.000Z rtc not set\n1970-01-01T00:00:00.000Z rtc garbage\n"
+	/// );
+	/// ```
+	pub fn set_clock(clock: Box<dyn Fn() -> SystemTime + Send + Sync>) {
+		*CLOCK.write().unwrap_or_else(|e| e.into_inner()) = Some(clock);
+	}
+	///Collects warnings and errors into a daily digest. Whenever the
+	/// (UTC) day changes, the unique messages seen on the previous day are
+	/// appended to the file at `path` along with how often each occurred.
+	/// `None` stops collecting
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{error, info, warn, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::Arc;
+	/// # use std::sync::atomic::{AtomicU64, Ordering};
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// // 2026-10-13 23:59:00 UTC
+	/// let secs = Arc::new(AtomicU64::new(1_791_935_940));
+	/// let clock = Arc::clone(&secs);
+	/// Minilog::set_clock(Box::new(move || {
+	///     UNIX_EPOCH + Duration::from_secs(clock.load(Ordering::SeqCst))
+	/// }));
+	/// Minilog::init(LevelFilter::Info, "minilog_daily_log_test.txt", "{level} - {msg}");
+	/// Minilog::set_daily_summary(Some("minilog_daily_summary_test.txt"));
+	/// error!("disk failure");
+	/// warn!("low memory");
+	/// error!("disk failure");
+	/// info!("not summarized");
+	/// secs.fetch_add(120, Ordering::SeqCst);
+	/// info!("first record of the next day");
+	/// let summary = fs::read_to_string("minilog_daily_summary_test.txt")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_daily_log_test.txt").expect("Unable to delete test file.");
+	/// # fs::remove_file("minilog_daily_summary_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     summary,
+	///     "Summary for 2026-10-13\nERROR x2: disk failure\nWARN x1: low memory\n"
+	/// );
+	/// ```
+	pub fn set_daily_summary(path: Option<&str>) {
+		*lock(&DAILY_SUMMARY) = path.map(|path| DailySummary {
+			path: path.to_owned(),
+			day: time::unix_day(now()),
+			counts: BTreeMap::new(),
+		});
+	}
+	///Sends an owned copy of every logged record to `sender`, in addition
+	/// to writing it out. Channels whose receiver has been dropped are
+	/// removed automatically
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{error, Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::sync::mpsc;
+	/// Minilog::init(LevelFilter::Info, "stderr", "{level} - {msg}");
+	/// let (sender, receiver) = mpsc::channel();
+	/// Minilog::add_channel_sink(sender);
+	/// error!("Disk {} failed", 2);
+	/// let record = receiver.recv().expect("No record received");
+	/// assert_eq!(record.level, Level::Error);
+	/// assert_eq!(record.message, "Disk 2 failed");
+	/// assert_eq!(record.file.as_deref(), Some(file!()));
+	/// assert!(record.line.is_some());
+	/// ```
+	pub fn add_channel_sink(sender: Sender<LogRecordOwned>) {
+		lock(&CHANNEL_SINKS).push(sender);
+	}
+	///Serves WebSocket connections at `bind_addr`, and streams every
+	/// formatted line as a text message to each connected client, in
+	/// addition to writing it out, e.g. for a live log dashboard. Lines are
+	/// sent from a thread of the server's own, so slow clients don't hold up
+	/// logging; a client that disconnects, or holds up a send for more than
+	/// 100ms, is dropped. Returns the address the server is listening on,
+	/// which tells the port when binding to port 0. Requires the `websocket`
+	/// feature
+	///
+	/// # Errors
+	/// Fails if it can't listen on `bind_addr`
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_websocket_test.txt", "{level} - {msg}");
+	/// let addr = Minilog::add_websocket("127.0.0.1:0").expect("Was unable to listen.");
+	/// assert!(Minilog::add_websocket(&addr.to_string()).is_err());
+	/// let (mut client, _) = tungstenite::connect(format!("ws://{}", addr))
+	///     .expect("Was unable to connect.");
+	/// // Wait for the server to register the client
+	/// while Minilog::websocket_clients() == 0 {
+	///     std::thread::sleep(std::time::Duration::from_millis(10));
+	/// }
+	/// info!("live");
+	/// let message = client.read().expect("Was unable to read a message.");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_websocket_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_websocket_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(message.into_text().unwrap().as_str(), "INFO - live");
+	/// assert_eq!(file_contents, "INFO - live\n");
+	/// ```
+	#[cfg(feature = "websocket")]
+	pub fn add_websocket(bind_addr: &str) -> io::Result<std::net::SocketAddr> {
+		let listener = std::net::TcpListener::bind(bind_addr)?;
+		let addr = listener.local_addr()?;
+		let clients = std::sync::Arc::new(Mutex::new(Vec::<WebSocketClient>::new()));
+		let (lines, receiver) = std::sync::mpsc::channel::<String>();
+		let broadcast_clients = clients.clone();
+		std::thread::spawn(move || {
+			for line in receiver {
+				// Clients that fail or are too slow to keep up are dropped
+				lock(&broadcast_clients).retain_mut(|client| {
+					client.send(tungstenite::Message::text(line.as_str())).is_ok()
+				});
+			}
+		});
+		let accept_clients = clients.clone();
+		std::thread::spawn(move || {
+			for stream in listener.incoming().flatten() {
+				let clients = accept_clients.clone();
+				std::thread::spawn(move || {
+					if stream.set_write_timeout(Some(WEBSOCKET_WRITE_TIMEOUT)).is_err() {
+						return;
+					}
+					if let Ok(client) = tungstenite::accept(stream) {
+						lock(&clients).push(client);
+					}
+				});
+			}
+		});
+		lock(&WEBSOCKET_SERVERS).push(WebSocketServer { lines, clients });
+		Ok(addr)
+	}
+	///Returns how many clients are connected to the WebSocket servers
+	/// started by `add_websocket`
+	#[cfg(feature = "websocket")]
+	pub fn websocket_clients() -> usize {
+		lock(&WEBSOCKET_SERVERS)
+			.iter()
+			.map(|server| lock(&server.clients).len())
+			.sum()
+	}
+	///Applies `level` every day between `start` and `end`, both given as
+	/// the time since midnight UTC, e.g. to log only errors overnight.
+	/// The window may wrap past midnight. The scheduled level can only
+	/// make logging quieter than the level set with `set_log_level`
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{error, info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::Arc;
+	/// # use std::sync::atomic::{AtomicU64, Ordering};
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// const HOUR: u64 = 60 * 60;
+	/// // 2026-10-14 21:00:00 UTC
+	/// let secs = Arc::new(AtomicU64::new(1_792_011_600));
+	/// let clock = Arc::clone(&secs);
+	/// Minilog::set_clock(Box::new(move || {
+	///     UNIX_EPOCH + Duration::from_secs(clock.load(Ordering::SeqCst))
+	/// }));
+	/// Minilog::init(LevelFilter::Info, "minilog_schedule_test.txt", "{level} - {msg}");
+	/// Minilog::set_schedule(
+	///     Duration::from_secs(22 * HOUR),
+	///     Duration::from_secs(6 * HOUR),
+	///     LevelFilter::Error,
+	/// );
+	/// info!("before quiet hours");
+	/// secs.fetch_add(2 * HOUR, Ordering::SeqCst);
+	/// info!("during quiet hours");
+	/// error!("error during quiet hours");
+	/// secs.fetch_add(8 * HOUR, Ordering::SeqCst);
+	/// info!("after quiet hours");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_schedule_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_schedule_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "INFO - before quiet hours\nERROR - error during quiet hours\nINFO - after quiet hours\n"
+	/// );
+	/// ```
+	pub fn set_schedule(start: Duration, end: Duration, level: LevelFilter) {
+		*lock(&SCHEDULE) = Some(Schedule {
+			start: start.as_secs() % time::SECS_PER_DAY as u64,
+			end: end.as_secs() % time::SECS_PER_DAY as u64,
+			level,
+		});
+	}
+	///Removes the schedule set with `set_schedule`
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// Minilog::clear_schedule();
+	/// ```
+	pub fn clear_schedule() {
+		*lock(&SCHEDULE) = None;
+	}
+	///Writes lines in `encoding` instead of UTF-8, including their trailing
+	/// newline, for consumers that expect UTF-16. Framed records and the
+	/// record prefix and suffix are written as they are
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{Encoding, Minilog};
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_encoding_test.txt", "{level} {msg}");
+	/// Minilog::set_encoding(Encoding::Utf16Le);
+	/// info!("hi");
+	/// let file_contents =
+	///     fs::read("minilog_encoding_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_encoding_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, b"I\0N\0F\0O\0 \0h\0i\0\n\0");
+	/// ```
+	pub fn set_encoding(encoding: Encoding) {
+		*ENCODING.write().unwrap_or_else(|e| e.into_inner()) = encoding;
+	}
+	///Writes each record as a frame made of its length in bytes, as a
+	/// big-endian `u32`, followed by the formatted line without a trailing
+	/// newline. Unlike newlines, frame boundaries stay unambiguous when
+	/// messages contain newlines. Read frames back with `read_frames`
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs::{self, File};
+	/// Minilog::init(LevelFilter::Info, "minilog_framed_test.txt", "{level} - {msg}");
+	/// Minilog::set_framed(true);
+	/// info!("first line\nsecond line");
+	/// info!("single line");
+	/// let file = File::open("minilog_framed_test.txt").expect("Was unable to open file.");
+	/// let records: Vec<String> = Minilog::read_frames(file)
+	///     .collect::<Result<_, _>>()
+	///     .expect("Was unable to read frames.");
+	/// # fs::remove_file("minilog_framed_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(records, ["INFO - first line\nsecond line", "INFO - single line"]);
+	/// ```
+	pub fn set_framed(framed: bool) {
+		FRAMED.store(framed, Ordering::Relaxed);
+	}
+	///Makes the log file hold only what was logged since the last flush,
+	/// for small status files that are rewritten periodically rather than
+	/// appended to. Records are kept in memory until `log::logger().flush()`
+	/// is called, which writes them to a temporary file next to the log
+	/// file and renames it over the log file, so readers always see a
+	/// complete status and never a partially written one
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::thread;
+	/// Minilog::init(LevelFilter::Info, "minilog_atomic_test.txt", "{msg}");
+	/// Minilog::set_atomic_rewrite(true);
+	/// let reader = thread::spawn(|| {
+	///     for _ in 0..500 {
+	///         let contents = fs::read_to_string("minilog_atomic_test.txt").unwrap_or_default();
+	///         let complete = contents.lines().count() == 20 && contents.ends_with("end\n");
+	///         assert!(contents.is_empty() || complete);
+	///     }
+	/// });
+	/// for round in 0..200 {
+	///     for i in 0..19 {
+	///         info!("round {} worker {} ok", round, i);
+	///     }
+	///     info!("end");
+	///     log::logger().flush();
+	/// }
+	/// reader.join().expect("Reader saw a partial file");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_atomic_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_atomic_test.txt").expect("Unable to delete test file.");
+	/// assert!(file_contents.starts_with("round 199 worker 0 ok\n"));
+	/// ```
+	///
+	/// Turning it off writes out what's buffered, without losing records
+	/// logged meanwhile
+	///
+	/// ```
+	/// # use log::{info, Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::thread;
+	/// Minilog::init(LevelFilter::Info, "minilog_atomic_off_test.txt", "{msg}");
+	/// Minilog::set_atomic_rewrite(true);
+	/// let writer = thread::spawn(|| {
+	///     for i in 0..2000 {
+	///         info!("record {}", i);
+	///     }
+	/// });
+	/// while Minilog::level_count(Level::Info) < 1000 {
+	///     thread::yield_now();
+	/// }
+	/// Minilog::set_atomic_rewrite(false);
+	/// writer.join().expect("Writer panicked");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_atomic_off_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_atomic_off_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents.lines().count(), 2000);
+	/// ```
+	pub fn set_atomic_rewrite(atomic: bool) {
+		if atomic {
+			lock(&ATOMIC_BUFFER).get_or_insert_with(Vec::new);
+		} else {
+			// Write out what's buffered before going back to appending, with
+			// records held off until it's written
+			let target = lock(&TARGET);
+			let buffered = lock(&ATOMIC_BUFFER).take();
+			if let (Some(Target::File(path)), Some(contents)) = (target.as_ref(), buffered) {
+				replace_log_file(path, contents);
+			}
+		}
+	}
+	///Writes `prefix` before every record, e.g. the 0x1E record separator
+	/// of RFC 7464 JSON text sequences. Not used for framed records
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// let fmt = r#"{"level":"{level}","msg":"{msg:json}"}"#;
+	/// Minilog::init(LevelFilter::Info, "minilog_record_prefix_test.txt", fmt);
+	/// Minilog::set_record_prefix(b"\x1e");
+	/// info!("first");
+	/// info!("second");
+	/// let file_contents =
+	///     fs::read("minilog_record_prefix_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_record_prefix_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     b"\x1e{\"level\":\"INFO\",\"msg\":\"first\"}\n\x1e{\"level\":\"INFO\",\"msg\":\"second\"}\n"
+	/// );
+	/// ```
+	pub fn set_record_prefix(prefix: &[u8]) {
+		*lock(&RECORD_PREFIX) = prefix.to_vec();
+	}
+	///Writes `suffix` after every record's trailing newline. Not used for
+	/// framed records
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// Minilog::set_record_suffix(b"\0");
+	/// ```
+	pub fn set_record_suffix(suffix: &[u8]) {
+		*lock(&RECORD_SUFFIX) = suffix.to_vec();
+	}
+	///Decodes the records from a stream written with `set_framed(true)`
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// let bytes = [0, 0, 0, 5, b'h', b'e', b'l', b'l', b'o'];
+	/// let records: Vec<String> = Minilog::read_frames(&bytes[..])
+	///     .collect::<Result<_, _>>()
+	///     .expect("Was unable to read frames.");
+	/// assert_eq!(records, ["hello"]);
+	/// // A header claiming 4 GiB on a stream cut short is an error
+	/// let truncated = [0xff, 0xff, 0xff, 0xff, b'h', b'i'];
+	/// let error = Minilog::read_frames(&truncated[..]).next().unwrap().unwrap_err();
+	/// assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+	/// ```
+	pub fn read_frames<R: Read>(reader: R) -> Frames<R> {
+		Frames {
+			reader,
+			failed: false,
+		}
+	}
+	///Writes each record as a length-delimited Protocol Buffers
+	/// `LogRecord` message, for pipelines that take protobuf, instead of a
+	/// formatted line. The schema is
+	///
+	/// ```proto
+	/// message LogRecord {
+	///   uint32 level = 1;            // 1 for ERROR up to 5 for TRACE
+	///   int64 timestamp_millis = 2;  // since the Unix epoch
+	///   string message = 3;
+	///   map<string, string> fields = 4;
+	/// }
+	/// ```
+	///
+	/// with the fields of the child logger and context provider. Read the
+	/// records back with `read_protobuf`. Requires the `protobuf` feature
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, warn, Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs::{self, File};
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// let logged_at = UNIX_EPOCH + Duration::from_millis(1_792_011_600_042);
+	/// Minilog::init(LevelFilter::Info, "minilog_protobuf_test.txt", "{level} - {msg}");
+	/// Minilog::set_clock(Box::new(move || logged_at));
+	/// Minilog::set_protobuf(true);
+	/// info!("started");
+	/// Minilog::child(&[("component", "storage")]).log(Level::Warn, "disk\nfull");
+	/// let file = File::open("minilog_protobuf_test.txt").expect("Was unable to open file.");
+	/// let records: Vec<_> = Minilog::read_protobuf(file)
+	///     .collect::<Result<_, _>>()
+	///     .expect("Was unable to read records.");
+	/// # fs::remove_file("minilog_protobuf_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(records.len(), 2);
+	/// assert_eq!((records[0].level, records[0].message.as_str()), (Level::Info, "started"));
+	/// assert_eq!(records[0].timestamp, logged_at);
+	/// assert!(records[0].fields.is_empty());
+	/// assert_eq!((records[1].level, records[1].message.as_str()), (Level::Warn, "disk\nfull"));
+	/// assert_eq!(records[1].fields, [("component".to_owned(), "storage".to_owned())]);
+	/// ```
+	///
+	/// A record whose context provider panics follows `set_on_format_error`
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{Minilog, OnFormatError};
+	/// # use std::fs::{self, File};
+	/// Minilog::init(LevelFilter::Info, "minilog_protobuf_panic_test.txt", "{msg}");
+	/// Minilog::set_protobuf(true);
+	/// Minilog::set_context_provider(Box::new(|| panic!("context lookup failed")));
+	/// info!("raw");
+	/// Minilog::set_on_format_error(OnFormatError::Skip);
+	/// info!("skipped");
+	/// Minilog::set_on_format_error(OnFormatError::Placeholder("<unformattable>".to_owned()));
+	/// info!("replaced");
+	/// let file = File::open("minilog_protobuf_panic_test.txt").expect("Was unable to open file.");
+	/// let records: Vec<_> = Minilog::read_protobuf(file)
+	///     .collect::<Result<_, _>>()
+	///     .expect("Was unable to read records.");
+	/// # fs::remove_file("minilog_protobuf_panic_test.txt").expect("Unable to delete test file.");
+	/// let messages: Vec<_> = records.iter().map(|record| record.message.as_str()).collect();
+	/// assert_eq!(messages, ["raw", "<unformattable>"]);
+	/// assert!(records.iter().all(|record| record.fields.is_empty()));
+	/// ```
+	#[cfg(feature = "protobuf")]
+	pub fn set_protobuf(protobuf: bool) {
+		PROTOBUF.store(protobuf, Ordering::Relaxed);
+	}
+	///Decodes the records from a stream written with `set_protobuf(true)`.
+	/// Requires the `protobuf` feature
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::Level;
+	/// # use minilog::Minilog;
+	/// let bytes = [7, 0x08, 1, 0x1a, 3, b'b', b'a', b'd'];
+	/// let records: Vec<_> = Minilog::read_protobuf(&bytes[..])
+	///     .collect::<Result<_, _>>()
+	///     .expect("Was unable to read records.");
+	/// assert_eq!((records[0].level, records[0].message.as_str()), (Level::Error, "bad"));
+	/// ```
+	#[cfg(feature = "protobuf")]
+	pub fn read_protobuf<R: Read>(reader: R) -> ProtobufRecords<R> {
+		ProtobufRecords {
+			reader,
+			failed: false,
+		}
+	}
+	///Moves the current log file to `path` and starts a fresh, empty log
+	/// file in its place, for handing the old contents off to a log shipper.
+	/// No record is lost or split between the two files, since writes are
+	/// held off until the new file exists. Returns the snapshot's path
+	///
+	/// # Errors
+	/// Fails if the logger isn't writing to a file, or if the file can't be
+	/// renamed or recreated
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_snapshot_test.txt", "{level} - {msg}");
+	/// info!("before snapshot");
+	/// let snapshot = Minilog::snapshot_to("minilog_snapshot_test.1.txt")
+	///     .expect("Was unable to take a snapshot.");
+	/// let active_after_snapshot =
+	///     fs::read_to_string("minilog_snapshot_test.txt").expect("Was unable to read file.");
+	/// info!("after snapshot");
+	/// let snapshot_contents = fs::read_to_string(&snapshot).expect("Was unable to read file.");
+	/// let active_contents =
+	///     fs::read_to_string("minilog_snapshot_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_snapshot_test.txt").expect("Unable to delete test file.");
+	/// # fs::remove_file(&snapshot).expect("Unable to delete test file.");
+	/// assert_eq!(snapshot_contents, "INFO - before snapshot\n");
+	/// assert_eq!(active_after_snapshot, "");
+	/// assert_eq!(active_contents, "INFO - after snapshot\n");
+	/// ```
+	pub fn snapshot_to<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
+		let sticky: Vec<Vec<u8>> =
+			lock(&STICKY_LINES).iter().map(|line| unrecorded_line_bytes(line)).collect();
+		let target = lock(&TARGET);
+		let active = match target.as_ref() {
+			Some(Target::File(active)) => active,
+			_ => {
+				return Err(io::Error::new(
+					io::ErrorKind::Unsupported,
+					"Minilog isn't logging to a file",
+				))
+			}
+		};
+		fs::rename(active, &path)?;
+		OpenOptions::new().append(true).create(true).open(active)?;
+		for bytes in sticky {
+			target.as_ref().unwrap().write(&bytes);
+		}
+		Ok(path.as_ref().to_path_buf())
+	}
+	///Counts the runs of the program in the file at `path`, to tell runs
+	/// apart in a shared log file. Each call adds one to the number stored
+	/// in the file, starting from 1, and renders the new number with the
+	/// `{run}` token, so call it once at startup
+	///
+	/// # Panics
+	/// Panics if it can't write to the file
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_run_test.txt", "run {run}: {msg}");
+	/// Minilog::set_run_counter_file("minilog_run_test.count");
+	/// info!("started");
+	/// // The next run of the program
+	/// Minilog::set_run_counter_file("minilog_run_test.count");
+	/// info!("started");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_run_test.txt").expect("Was unable to read file.");
+	/// let count = fs::read_to_string("minilog_run_test.count").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_run_test.txt").expect("Unable to delete test file.");
+	/// # fs::remove_file("minilog_run_test.count").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "run 1: started\nrun 2: started\n");
+	/// assert_eq!(count, "2");
+	/// ```
+	pub fn set_run_counter_file(path: &str) {
+		let previous = fs::read_to_string(path)
+			.ok()
+			.and_then(|count| count.trim().parse::<u64>().ok())
+			.unwrap_or(0);
+		let run = previous + 1;
+		if let Err(e) = fs::write(path, run.to_string()) {
+			panic!("{}: Failed to write to run counter file {}", e, path);
+		}
+		RUN.store(run, Ordering::Relaxed);
+	}
+	///Writes `line` to the log as it is, without the format string, and
+	/// again at the top of every fresh log file started by `snapshot_to`,
+	/// so context such as a session banner is at hand in every file. It is
+	/// signed, framed or encoded in protobuf, like an info record, if
+	/// records are
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_sticky_test.txt", "{level} - {msg}");
+	/// Minilog::add_sticky_line("== session 42, config v3 ==");
+	/// info!("before snapshot");
+	/// let snapshot = Minilog::snapshot_to("minilog_sticky_test.1.txt")
+	///     .expect("Was unable to take a snapshot.");
+	/// info!("after snapshot");
+	/// let snapshot_contents = fs::read_to_string(&snapshot).expect("Was unable to read file.");
+	/// let active_contents =
+	///     fs::read_to_string("minilog_sticky_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_sticky_test.txt").expect("Unable to delete test file.");
+	/// # fs::remove_file(&snapshot).expect("Unable to delete test file.");
+	/// assert_eq!(snapshot_contents, "== session 42, config v3 ==\nINFO - before snapshot\n");
+	/// assert_eq!(active_contents, "== session 42, config v3 ==\nINFO - after snapshot\n");
+	/// ```
+	///
+	/// Sticky lines are encoded like records
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::{Encoding, Minilog};
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_sticky_utf16_test.txt", "{msg}");
+	/// Minilog::set_encoding(Encoding::Utf16Le);
+	/// Minilog::add_sticky_line("hi");
+	/// let snapshot = Minilog::snapshot_to("minilog_sticky_utf16_test.1.txt")
+	///     .expect("Was unable to take a snapshot.");
+	/// info!("hi");
+	/// let snapshot_contents = fs::read(&snapshot).expect("Was unable to read file.");
+	/// let active_contents =
+	///     fs::read("minilog_sticky_utf16_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_sticky_utf16_test.txt").expect("Unable to delete test file.");
+	/// # fs::remove_file(&snapshot).expect("Unable to delete test file.");
+	/// assert_eq!(snapshot_contents, b"h\0i\0\n\0");
+	/// assert_eq!(active_contents, b"h\0i\0\n\0h\0i\0\n\0");
+	/// ```
+	///
+	/// and framed like them
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs::{self, File};
+	/// Minilog::init(LevelFilter::Info, "minilog_sticky_framed_test.txt", "{msg}");
+	/// Minilog::set_framed(true);
+	/// info!("one");
+	/// Minilog::add_sticky_line("== banner ==");
+	/// info!("two");
+	/// let file = File::open("minilog_sticky_framed_test.txt").expect("Was unable to open file.");
+	/// let records: Vec<String> = Minilog::read_frames(file)
+	///     .collect::<Result<_, _>>()
+	///     .expect("Was unable to read frames.");
+	/// # fs::remove_file("minilog_sticky_framed_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(records, ["one", "== banner ==", "two"]);
+	/// ```
+	pub fn add_sticky_line(line: &str) {
+		write_output(&unrecorded_line_bytes(line));
+		lock(&STICKY_LINES).push(line.to_owned());
+	}
+	///Follows the log file like `tail -F`, yielding each line written from
+	/// now on. When the file is replaced, e.g. by `snapshot_to`, the rest of
+	/// the old file is read before switching to the new one, so lines come
+	/// out in the order they were logged. Iteration blocks while waiting for
+	/// new lines
+	///
+	/// # Errors
+	/// Fails if the logger isn't writing to a file, or if the file can't be
+	/// opened
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_follow_test.txt", "{level} - {msg}");
+	/// info!("before following");
+	/// let follower = Minilog::follow().expect("Was unable to follow the log.");
+	/// info!("first generation");
+	/// let snapshot = Minilog::snapshot_to("minilog_follow_test.1.txt")
+	///     .expect("Was unable to take a snapshot.");
+	/// info!("second generation");
+	/// let lines: Vec<String> = follower.take(2).collect();
+	/// # fs::remove_file("minilog_follow_test.txt").expect("Unable to delete test file.");
+	/// # fs::remove_file(&snapshot).expect("Unable to delete test file.");
+	/// assert_eq!(lines, ["INFO - first generation", "INFO - second generation"]);
+	/// ```
+	pub fn follow() -> io::Result<Follow> {
+		let target = lock(&TARGET);
+		let path = match target.as_ref() {
+			Some(Target::File(path)) => PathBuf::from(path),
+			_ => {
+				return Err(io::Error::new(
+					io::ErrorKind::Unsupported,
+					"Minilog isn't logging to a file",
+				))
+			}
+		};
+		let mut file = OpenOptions::new().read(true).append(true).create(true).open(&path)?;
+		file.seek(io::SeekFrom::End(0))?;
+		Ok(Follow {
+			id: file.metadata().ok().as_ref().and_then(file_id),
+			path,
+			file,
+			pending: Vec::new(),
+		})
+	}
+	///Logs an HTTP request as a line of `key=value` fields, so that
+	/// request logging looks the same everywhere. Latency is in
+	/// milliseconds, and values containing spaces or quotes are quoted
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::time::Duration;
+	/// Minilog::init(LevelFilter::Info, "minilog_request_test.txt", "{level} {msg}");
+	/// Minilog::log_request(Level::Info, "GET", "/api/users", 200, Duration::from_micros(12_500));
+	/// Minilog::log_request(Level::Warn, "POST", "/search?q=a b", 503, Duration::from_secs(2));
+	/// let file_contents =
+	///     fs::read_to_string("minilog_request_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_request_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "INFO method=GET path=/api/users status=200 latency_ms=12.500\n\
+	///      WARN method=POST path=\"/search?q=a b\" status=503 latency_ms=2000.000\n"
+	/// );
+	/// ```
+	#[track_caller]
+	pub fn log_request(level: Level, method: &str, path: &str, status: u16, latency: Duration) {
+		log_from_caller(
+			level,
+			format_args!(
+				"method={} path={} status={} latency_ms={:.3}",
+				kv_value(method),
+				kv_value(path),
+				status,
+				latency.as_secs_f64() * 1000.0
+			),
+		);
+	}
+	///Logs a metric at `Info` as a line of `key=value` fields starting
+	/// with `type=metric`, so that metrics can share a pipeline with logs
+	/// and be told apart from them downstream
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::LevelFilter;
+	/// # use minilog::{MetricKind, Minilog};
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_metric_test.txt", "{level} {msg}");
+	/// Minilog::log_metric("requests_served", 1024.0, MetricKind::Counter);
+	/// Minilog::log_metric("queue length", 2.5, MetricKind::Gauge);
+	/// let file_contents =
+	///     fs::read_to_string("minilog_metric_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_metric_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "INFO type=metric kind=counter name=requests_served value=1024\n\
+	///      INFO type=metric kind=gauge name=\"queue length\" value=2.5\n"
+	/// );
+	/// ```
+	#[track_caller]
+	pub fn log_metric(name: &str, value: f64, kind: MetricKind) {
+		log_from_caller(
+			Level::Info,
+			format_args!(
+				"type=metric kind={} name={} value={}",
+				kind.name(),
+				kv_value(name),
+				value
+			),
+		);
+	}
+	///Logs an access in the Common Log Format used by Apache and other web
+	/// servers: `host ident authuser [date] "request" status bytes`, with
+	/// `-` for unknown fields and the date taken from the logger clock.
+	/// The line is logged at `Info` as the message, so use a format string
+	/// of just `{msg}` to write plain CLF lines
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::LevelFilter;
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// Minilog::set_clock(Box::new(|| UNIX_EPOCH + Duration::from_secs(971_186_136)));
+	/// Minilog::init(LevelFilter::Info, "minilog_access_test.txt", "{msg}");
+	/// Minilog::log_access("127.0.0.1", None, Some("frank"), "GET /apache_pb.gif HTTP/1.0", 200, Some(2326));
+	/// Minilog::log_access("10.0.0.2", None, None, "HEAD / HTTP/1.1", 304, None);
+	/// let file_contents =
+	///     fs::read_to_string("minilog_access_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_access_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "127.0.0.1 - frank [10/Oct/2000:13:55:36 +0000] \"GET /apache_pb.gif HTTP/1.0\" 200 2326\n\
+	///      10.0.0.2 - - [10/Oct/2000:13:55:36 +0000] \"HEAD / HTTP/1.1\" 304 -\n"
+	/// );
+	/// ```
+	///
+	/// Quotes and backslashes in the request are escaped with a backslash,
+	/// so the quoted field can't be ended early
+	///
+	/// ```
+	/// # use log::LevelFilter;
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// Minilog::set_clock(Box::new(|| UNIX_EPOCH + Duration::from_secs(971_186_136)));
+	/// Minilog::init(LevelFilter::Info, "minilog_access_escape_test.txt", "{msg}");
+	/// Minilog::log_access("10.0.0.3", None, None, r#"GET /a\" 200 1 HTTP/1.0"#, 404, None);
+	/// let file_contents =
+	///     fs::read_to_string("minilog_access_escape_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_access_escape_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "10.0.0.3 - - [10/Oct/2000:13:55:36 +0000] \"GET /a\\\\\\\" 200 1 HTTP/1.0\" 404 -\n"
+	/// );
+	/// ```
+	#[track_caller]
+	pub fn log_access(
+		host: &str,
+		ident: Option<&str>,
+		authuser: Option<&str>,
+		request: &str,
+		status: u16,
+		bytes: Option<u64>,
+	) {
+		log_from_caller(
+			Level::Info,
+			format_args!(
+				"{} {} {} [{}] \"{}\" {} {}",
+				host,
+				ident.unwrap_or("-"),
+				authuser.unwrap_or("-"),
+				time::format_clf(now()),
+				request.replace('\\', "\\\\").replace('"', "\\\""),
+				status,
+				bytes.map_or_else(|| "-".to_owned(), |bytes| bytes.to_string())
+			),
+		);
+	}
+	///Logs the progress of a long operation as `key: N% (current/total)`,
+	/// but only once the whole percentage has moved on by the progress step
+	/// (1 by default) since the last line logged for `key`. The first call
+	/// for a key, reaching 100% and going backwards are always logged, so
+	/// driving an operation from 0 to 100% logs at most 101 lines
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_progress_test.txt", "{msg}");
+	/// for current in 0..=2000 {
+	///     Minilog::log_progress(Level::Info, "import", current, 2000);
+	/// }
+	/// let file_contents =
+	///     fs::read_to_string("minilog_progress_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_progress_test.txt").expect("Unable to delete test file.");
+	/// let lines: Vec<&str> = file_contents.lines().collect();
+	/// assert_eq!(lines.len(), 101);
+	/// assert_eq!(lines[0], "import: 0% (0/2000)");
+	/// assert_eq!(lines[1], "import: 1% (20/2000)");
+	/// assert_eq!(lines[100], "import: 100% (2000/2000)");
+	/// ```
+	#[track_caller]
+	pub fn log_progress(level: Level, key: &str, current: u64, total: u64) {
+		let percent = match total {
+			0 => 100,
+			_ => (u128::from(current.min(total)) * 100 / u128::from(total)) as u64,
+		};
+		let step = PROGRESS_STEP.load(Ordering::Relaxed).max(1);
+		let mut progress = lock(&PROGRESS);
+		let due = match progress.get(key) {
+			Some(&last) => percent < last || percent >= last + step || (percent == 100 && last != 100),
+			None => true,
+		};
+		if due {
+			progress.insert(key.to_owned(), percent);
+			drop(progress);
+			log_from_caller(level, format_args!("{}: {}% ({}/{})", key, percent, current, total));
+		}
+	}
+	///Sets how many percent an operation has to advance by before
+	/// `log_progress` logs it again
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// Minilog::set_progress_step(10);
+	/// ```
+	pub fn set_progress_step(percent: u64) {
+		PROGRESS_STEP.store(percent, Ordering::Relaxed);
+	}
+	///Raises the level to `level` for `duration` after each error, to
+	/// capture what happens around it in more detail. Later errors extend
+	/// the window. Once it runs out the logger goes back to its own level
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{debug, error, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::Arc;
+	/// # use std::sync::atomic::{AtomicU64, Ordering};
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// let secs = Arc::new(AtomicU64::new(1_792_011_600));
+	/// let clock = Arc::clone(&secs);
+	/// Minilog::set_clock(Box::new(move || {
+	///     UNIX_EPOCH + Duration::from_secs(clock.load(Ordering::SeqCst))
+	/// }));
+	/// Minilog::init(LevelFilter::Info, "minilog_error_burst_test.txt", "{level} - {msg}");
+	/// Minilog::set_error_burst(Duration::from_secs(30), LevelFilter::Debug);
+	/// debug!("before the error");
+	/// error!("connection lost");
+	/// debug!("retrying");
+	/// secs.fetch_add(31, Ordering::SeqCst);
+	/// debug!("after the window");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_error_burst_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_error_burst_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "ERROR - connection lost\nDEBUG - retrying\n");
+	/// assert_eq!(Minilog::log_level(), Some(log::Level::Info));
+	/// ```
+	///
+	/// The window starts when the error is logged, even if it is backfilled
+	/// with `log_at_time`
+	///
+	/// ```
+	/// # use log::{debug, Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::Arc;
+	/// # use std::sync::atomic::{AtomicU64, Ordering};
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// let secs = Arc::new(AtomicU64::new(1_792_011_600));
+	/// let clock = Arc::clone(&secs);
+	/// Minilog::set_clock(Box::new(move || {
+	///     UNIX_EPOCH + Duration::from_secs(clock.load(Ordering::SeqCst))
+	/// }));
+	/// Minilog::init(LevelFilter::Info, "minilog_backfilled_burst_test.txt", "{level} - {msg}");
+	/// Minilog::set_error_burst(Duration::from_secs(30), LevelFilter::Debug);
+	/// let event_time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+	/// Minilog::log_at_time(Level::Error, "imported failure", event_time);
+	/// debug!("retrying");
+	/// secs.fetch_add(31, Ordering::SeqCst);
+	/// debug!("after the window");
+	/// let file_contents = fs::read_to_string("minilog_backfilled_burst_test.txt")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_backfilled_burst_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "ERROR - imported failure\nDEBUG - retrying\n");
+	/// assert_eq!(Minilog::log_level(), Some(Level::Info));
+	/// ```
+	pub fn set_error_burst(duration: Duration, level: LevelFilter) {
+		*lock(&ERROR_BURST) = Some((duration, level));
+	}
+	///Logs at `level` for the first `after` since the logger was
+	/// initialized, then settles down to the logger's own level
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{debug, info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::Arc;
+	/// # use std::sync::atomic::{AtomicU64, Ordering};
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// let secs = Arc::new(AtomicU64::new(1_792_011_600));
+	/// let clock = Arc::clone(&secs);
+	/// Minilog::set_clock(Box::new(move || {
+	///     UNIX_EPOCH + Duration::from_secs(clock.load(Ordering::SeqCst))
+	/// }));
+	/// Minilog::init(LevelFilter::Info, "minilog_startup_level_test.txt", "{level} - {msg}");
+	/// Minilog::set_startup_level(LevelFilter::Debug, Duration::from_secs(60));
+	/// debug!("loading config");
+	/// secs.fetch_add(61, Ordering::SeqCst);
+	/// debug!("settled");
+	/// info!("running");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_startup_level_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_startup_level_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "DEBUG - loading config\nINFO - running\n");
+	/// ```
+	///
+	/// Called before the logger is initialized, the window starts now and
+	/// ends at the logger's own level
+	///
+	/// ```
+	/// # use log::{debug, info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::Arc;
+	/// # use std::sync::atomic::{AtomicU64, Ordering};
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// let secs = Arc::new(AtomicU64::new(1_792_011_600));
+	/// let clock = Arc::clone(&secs);
+	/// Minilog::set_clock(Box::new(move || {
+	///     UNIX_EPOCH + Duration::from_secs(clock.load(Ordering::SeqCst))
+	/// }));
+	/// Minilog::set_startup_level(LevelFilter::Debug, Duration::from_secs(60));
+	/// Minilog::init(LevelFilter::Info, "minilog_early_startup_level_test.txt", "{level} - {msg}");
+	/// debug!("loading config");
+	/// secs.fetch_add(61, Ordering::SeqCst);
+	/// debug!("settled");
+	/// info!("running");
+	/// let file_contents = fs::read_to_string("minilog_early_startup_level_test.txt")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_early_startup_level_test.txt")
+	/// #     .expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "DEBUG - loading config\nINFO - running\n");
+	/// ```
+	pub fn set_startup_level(level: LevelFilter, after: Duration) {
+		let init_time = lock(&INIT_TIME).unwrap_or_else(now);
+		raise_level_until(level, init_time + after);
+	}
+	///Drops every record logged in the first `duration` since the logger
+	/// was initialized, for programs that are noisy while starting up
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{error, info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::Arc;
+	/// # use std::sync::atomic::{AtomicU64, Ordering};
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// let millis = Arc::new(AtomicU64::new(1_792_011_600_000));
+	/// let clock = Arc::clone(&millis);
+	/// Minilog::set_clock(Box::new(move || {
+	///     UNIX_EPOCH + Duration::from_millis(clock.load(Ordering::SeqCst))
+	/// }));
+	/// Minilog::init(LevelFilter::Info, "minilog_warmup_test.txt", "{level} - {msg}");
+	/// Minilog::set_warmup_suppress(Duration::from_millis(500));
+	/// error!("connecting");
+	/// millis.fetch_add(499, Ordering::SeqCst);
+	/// info!("still connecting");
+	/// millis.fetch_add(1, Ordering::SeqCst);
+	/// info!("ready");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_warmup_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_warmup_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "INFO - ready\n");
+	/// ```
+	pub fn set_warmup_suppress(duration: Duration) {
+		let init_time = lock(&INIT_TIME).unwrap_or_else(now);
+		*lock(&WARMUP_UNTIL) = Some(init_time + duration);
+	}
+	///Keeps the file at `path` up to date while the program logs, for
+	/// supervisors that watch its modification time. The file holds the
+	/// time it was last written, on a line of its own, and is rewritten by
+	/// the first record logged at least `interval` after the previous write
+	///
+	/// # Panics
+	/// Panics if it can't write the file
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::Arc;
+	/// # use std::sync::atomic::{AtomicU64, Ordering};
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// let secs = Arc::new(AtomicU64::new(1_792_011_600));
+	/// let clock = Arc::clone(&secs);
+	/// Minilog::set_clock(Box::new(move || {
+	///     UNIX_EPOCH + Duration::from_secs(clock.load(Ordering::SeqCst))
+	/// }));
+	/// Minilog::init(LevelFilter::Info, "minilog_liveness_test.txt", "{level} - {msg}");
+	/// Minilog::set_liveness_file("minilog_liveness_test.alive", Duration::from_secs(10));
+	/// let modified = || {
+	///     fs::metadata("minilog_liveness_test.alive").and_then(|m| m.modified()).unwrap()
+	/// };
+	/// let first_write = modified();
+	/// std::thread::sleep(Duration::from_millis(20));
+	/// info!("working");
+	/// assert_eq!(modified(), first_write);
+	/// secs.fetch_add(10, Ordering::SeqCst);
+	/// info!("still working");
+	/// let last_write = modified();
+	/// let contents = fs::read_to_string("minilog_liveness_test.alive").unwrap();
+	/// # fs::remove_file("minilog_liveness_test.txt").expect("Unable to delete test file.");
+	/// # fs::remove_file("minilog_liveness_test.alive").expect("Unable to delete test file.");
+	/// assert!(last_write > first_write);
+	/// assert_eq!(contents, "2026-10-14T21:00:10.000Z\n");
+	/// ```
+	///
+	/// Records backfilled with `log_at_time` are touched at the logger's
+	/// time
+	///
+	/// ```
+	/// # use log::{Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::Arc;
+	/// # use std::sync::atomic::{AtomicU64, Ordering};
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// let secs = Arc::new(AtomicU64::new(1_792_011_600));
+	/// let clock = Arc::clone(&secs);
+	/// Minilog::set_clock(Box::new(move || {
+	///     UNIX_EPOCH + Duration::from_secs(clock.load(Ordering::SeqCst))
+	/// }));
+	/// Minilog::init(LevelFilter::Info, "minilog_backfill_live_test.txt", "{msg}");
+	/// Minilog::set_liveness_file("minilog_backfill_live_test.alive", Duration::from_secs(10));
+	/// let next_year = UNIX_EPOCH + Duration::from_secs(1_792_011_600 + 365 * 86_400);
+	/// Minilog::log_at_time(Level::Info, "scheduled", next_year);
+	/// secs.fetch_add(10, Ordering::SeqCst);
+	/// Minilog::log_at_time(Level::Info, "scheduled", next_year);
+	/// let contents = fs::read_to_string("minilog_backfill_live_test.alive").unwrap();
+	/// # fs::remove_file("minilog_backfill_live_test.txt").expect("Unable to delete test file.");
+	/// # fs::remove_file("minilog_backfill_live_test.alive").expect("Unable to delete test file.");
+	/// assert_eq!(contents, "2026-10-14T21:00:10.000Z\n");
+	/// ```
+	pub fn set_liveness_file(path: &str, interval: Duration) {
+		let now = now();
+		let mut liveness = Liveness {
+			path: path.to_owned(),
+			interval,
+			touched: now,
+		};
+		liveness.touch(now);
+		*lock(&LIVENESS) = Some(liveness);
+	}
+	///Promotes records whose message matches the regular expression
+	/// `pattern` to `level`, for libraries that log problems at the wrong
+	/// level. The record is then handled entirely as a `level` record,
+	/// including filtering and counters. Rules never demote a record, and
+	/// when several match, the most severe level wins. Requires the
+	/// `escalation` feature
+	///
+	/// Only records that pass the level given to `init` or `set_log_level`
+	/// reach the logger, since the `log` macros drop the rest before calling
+	/// it, so they can't be escalated. To catch a library's Info records,
+	/// log at Info or more verbose
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_escalation_test.txt", "{level} - {msg}");
+	/// Minilog::add_escalation("OutOfMemory", Level::Error).expect("Invalid pattern");
+	/// info!("worker failed: OutOfMemory");
+	/// info!("worker done");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_escalation_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_escalation_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "ERROR - worker failed: OutOfMemory\nINFO - worker done\n");
+	/// assert_eq!(Minilog::level_count(Level::Error), 1);
+	/// assert_eq!(Minilog::level_count(Level::Info), 1);
+	/// ```
+	///
+	/// ```
+	/// # use log::{info, Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Warn, "minilog_escalation_filtered_test.txt", "{level} - {msg}");
+	/// Minilog::add_escalation("OutOfMemory", Level::Error).expect("Invalid pattern");
+	/// // Dropped by the log macro before Minilog sees it
+	/// info!("worker failed: OutOfMemory");
+	/// # let _ = fs::remove_file("minilog_escalation_filtered_test.txt");
+	/// assert_eq!(Minilog::level_count(Level::Error), 0);
+	/// ```
+	#[cfg(feature = "escalation")]
+	pub fn add_escalation(pattern: &str, level: Level) -> Result<(), regex::Error> {
+		let pattern = Regex::new(pattern)?;
+		ESCALATIONS.write().unwrap_or_else(|e| e.into_inner()).push((pattern, level));
+		Ok(())
+	}
+	///Keeps only a random share of the records at some levels, e.g. 10% of
+	/// Info and 1% of Debug, given as a probability between 0 and 1. Levels
+	/// that aren't in `keep` are never dropped, so errors can be kept while
+	/// noisy levels are thinned out. Replaces any previous sampling
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{debug, error, Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::collections::BTreeMap;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Debug, "minilog_sampling_test.txt", "{level} - {msg}");
+	/// let mut keep = BTreeMap::new();
+	/// keep.insert(Level::Info, 0.1);
+	/// keep.insert(Level::Debug, 0.01);
+	/// Minilog::set_severity_sampling(keep);
+	/// for i in 0..1000 {
+	///     error!("error {}", i);
+	///     debug!("debug {}", i);
+	/// }
+	/// # fs::remove_file("minilog_sampling_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(Minilog::level_count(Level::Error), 1000);
+	/// assert!(Minilog::level_count(Level::Debug) < 100);
+	/// ```
+	pub fn set_severity_sampling(keep: BTreeMap<Level, f64>) {
+		let seed = time::unix_millis(SystemTime::now()) as u64 ^ u64::from(std::process::id());
+		SAMPLING_STATE.store(seed, Ordering::Relaxed);
+		*SAMPLING.write().unwrap_or_else(|e| e.into_inner()) = keep;
+	}
+	///Only logs the 1st, 2nd, 4th, 8th and so on occurrences of each
+	/// message, so that an error that keeps repeating still shows up as
+	/// ongoing without flooding the log. Every distinct message is counted
+	/// until this is turned off again, which forgets the counts
 	///
 	/// # Examples
 	///
 	/// ```
-	/// # use log::LevelFilter;
+	/// # use log::{error, LevelFilter};
 	/// # use minilog::Minilog;
-	/// Minilog::init(LevelFilter::Info, "logs.txt", "{level} - {msg}");
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_exponential_dedup_test.txt", "{level} - {msg}");
+	/// Minilog::set_exponential_dedup(true);
+	/// for occurrence in 1..=20 {
+	///     error!("connection refused");
+	///     if occurrence == 3 {
+	///         error!("disk full");
+	///     }
+	/// }
+	/// let file_contents = fs::read_to_string("minilog_exponential_dedup_test.txt")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_exponential_dedup_test.txt").expect("Unable to delete test file.");
+	/// let refused = "ERROR - connection refused\n";
+	/// assert_eq!(
+	///     file_contents,
+	///     [refused, refused, "ERROR - disk full\n", refused, refused, refused].concat()
+	/// );
 	/// ```
-	pub fn init(
-		loglevel: LevelFilter,
-		logfile_name: &str,
-		fmt_string: &str,
-	) -> Result<(), SetLoggerError> {
-		set_boxed_logger(Box::new(Minilog {
-			logfile_name: logfile_name.to_owned(),
-			fmt_string: fmt_string.to_owned(),
-		}))
-		.map(|()| set_max_level(loglevel))
+	pub fn set_exponential_dedup(dedup: bool) {
+		*lock(&OCCURRENCES) = if dedup { Some(BTreeMap::new()) } else { None };
 	}
-	///Initializes a logger with default settings
+	///Returns how many records have been written at `level`
 	///
 	/// # Examples
+	///
 	/// ```
-	/// # use log::LevelFilter;
+	/// # use log::{warn, Level, LevelFilter};
 	/// # use minilog::Minilog;
-	/// Minilog::init_default();
+	/// Minilog::init(LevelFilter::Info, "stderr", "{level} - {msg}");
+	/// warn!("first");
+	/// warn!("second");
+	/// assert_eq!(Minilog::level_count(Level::Warn), 2);
+	/// assert_eq!(Minilog::level_count(Level::Error), 0);
 	/// ```
-	pub fn init_default() -> Result<(), SetLoggerError> {
-		set_boxed_logger(Box::new(Minilog {
-			logfile_name: "logs.txt".to_owned(),
-			fmt_string: "{level}: {msg}".to_owned(),
-		}))
-		.map(|()| set_max_level(LevelFilter::Trace))
+	pub fn level_count(level: Level) -> u64 {
+		LEVEL_COUNTS[level as usize - 1].load(Ordering::Relaxed)
 	}
-	///Sets the maximum level of log message to write
+	///Makes `shutdown` write a summary line with the number of records
+	/// logged at each level, for quick triage of CI and batch jobs. Like a
+	/// sticky line, it is encoded the way records are
 	///
 	/// # Examples
 	///
 	/// ```
-	/// # use log::LevelFilter;
+	/// # use log::{error, info, warn, LevelFilter};
+	/// # use minilog::{Minilog, SummaryFormat};
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_summary_test.txt", "{level} - {msg}");
+	/// Minilog::set_emit_summary_on_shutdown(true);
+	/// error!("failed");
+	/// warn!("slow");
+	/// warn!("slower");
+	/// info!("done");
+	/// Minilog::shutdown();
+	/// let file_contents =
+	///     fs::read_to_string("minilog_summary_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_summary_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents.lines().last(),
+	///     Some("SUMMARY errors=1 warnings=2 info=1 debug=0 trace=0")
+	/// );
+	/// ```
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
 	/// # use minilog::Minilog;
-	/// Minilog::set_log_level(LevelFilter::Info);
+	/// # use std::fs::{self, File};
+	/// Minilog::init(LevelFilter::Info, "minilog_framed_summary_test.txt", "{msg}");
+	/// Minilog::set_framed(true);
+	/// Minilog::set_emit_summary_on_shutdown(true);
+	/// info!("done");
+	/// Minilog::shutdown();
+	/// let file = File::open("minilog_framed_summary_test.txt").expect("Was unable to open file.");
+	/// let records: Vec<String> = Minilog::read_frames(file)
+	///     .collect::<Result<_, _>>()
+	///     .expect("Was unable to read frames.");
+	/// # fs::remove_file("minilog_framed_summary_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(records, ["done", "SUMMARY errors=0 warnings=0 info=1 debug=0 trace=0"]);
 	/// ```
-	pub fn set_log_level(loglevel: LevelFilter) {
-		set_max_level(loglevel);
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_prefixed_summary_test.txt", "{msg}");
+	/// Minilog::set_record_prefix(b"\x1e");
+	/// Minilog::set_emit_summary_on_shutdown(true);
+	/// info!("done");
+	/// Minilog::shutdown();
+	/// let file_contents = fs::read_to_string("minilog_prefixed_summary_test.txt")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_prefixed_summary_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "\x1edone\n\x1eSUMMARY errors=0 warnings=0 info=1 debug=0 trace=0\n"
+	/// );
+	/// ```
+	pub fn set_emit_summary_on_shutdown(emit: bool) {
+		EMIT_SUMMARY.store(emit, Ordering::Relaxed);
 	}
-	///Logs or panics if loglevel is too low
+	///Sets whether the shutdown summary is written as text or JSON
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{error, LevelFilter};
+	/// # use minilog::{Minilog, SummaryFormat};
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_summary_json_test.txt", "{level} - {msg}");
+	/// Minilog::set_emit_summary_on_shutdown(true);
+	/// Minilog::set_summary_format(SummaryFormat::Json);
+	/// error!("failed");
+	/// Minilog::shutdown();
+	/// let file_contents =
+	///     fs::read_to_string("minilog_summary_json_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_summary_json_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents.lines().last(),
+	///     Some(r#"{"summary":{"errors":1,"warnings":0,"info":0,"debug":0,"trace":0}}"#)
+	/// );
+	/// ```
+	pub fn set_summary_format(format: SummaryFormat) {
+		*SUMMARY_FORMAT.write().unwrap_or_else(|e| e.into_inner()) = format;
+	}
+	///Flushes the logger and writes the summary line, if enabled with
+	/// `set_emit_summary_on_shutdown`, along with the current day's
+	/// `set_daily_summary` digest so far. The global logger is never dropped,
+	/// so call this before the program exits
 	///
 	/// # Examples
 	///
 	/// ```
-	/// # use log::Level;
 	/// # use log::LevelFilter;
 	/// # use minilog::Minilog;
+	/// Minilog::init(LevelFilter::Info, "stderr", "{level} - {msg}");
+	/// Minilog::shutdown();
+	/// ```
+	///
+	/// ```
+	/// # use log::{error, LevelFilter};
+	/// # use minilog::Minilog;
 	/// # use std::fs;
-	/// Minilog::init(LevelFilter::Info, "minilog_output_test.txt", "{level} - {msg}");
-	/// Minilog::log_or_panic(Level::Error, "Error!");
-	/// # fs::remove_file("minilog_output_test.txt").expect("Unable to delete test file.");
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// Minilog::set_clock(Box::new(|| UNIX_EPOCH + Duration::from_secs(1_792_011_600)));
+	/// Minilog::init(LevelFilter::Info, "minilog_shutdown_daily_log_test.txt", "{level} - {msg}");
+	/// Minilog::set_daily_summary(Some("minilog_shutdown_daily_summary_test.txt"));
+	/// error!("disk failure");
+	/// Minilog::shutdown();
+	/// Minilog::shutdown();
+	/// let summary = fs::read_to_string("minilog_shutdown_daily_summary_test.txt")
+	///     .expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_shutdown_daily_log_test.txt").expect("Unable to delete test file.");
+	/// # fs::remove_file("minilog_shutdown_daily_summary_test.txt")
+	/// #     .expect("Unable to delete test file.");
+	/// assert_eq!(summary, "Summary for 2026-10-14\nERROR x1: disk failure\n");
 	/// ```
+	pub fn shutdown() {
+		if let Some(summary) = lock(&DAILY_SUMMARY).as_mut() {
+			summary.write();
+			summary.counts.clear();
+		}
+		if EMIT_SUMMARY.load(Ordering::Relaxed) {
+			Minilog::write_summary();
+		}
+		logger().flush();
+	}
+	/// Writes the summary line enabled by `set_emit_summary_on_shutdown`
+	fn write_summary() {
+		let names = ["errors", "warnings", "info", "debug", "trace"];
+		let counts = names
+			.iter()
+			.zip(&LEVEL_COUNTS)
+			.map(|(name, count)| (name, count.load(Ordering::Relaxed)));
+		let summary = match *SUMMARY_FORMAT.read().unwrap_or_else(|e| e.into_inner()) {
+			SummaryFormat::Text => {
+				let fields: Vec<String> =
+					counts.map(|(name, count)| format!("{}={}", name, count)).collect();
+				format!("SUMMARY {}", fields.join(" "))
+			}
+			SummaryFormat::Json => {
+				let fields: Vec<String> =
+					counts.map(|(name, count)| format!("\"{}\":{}", name, count)).collect();
+				format!("{{\"summary\":{{{}}}}}", fields.join(","))
+			}
+		};
+		write_output(&unrecorded_line_bytes(&summary));
+	}
+	///Returns the average time spent formatting and writing a record, to
+	/// tell whether logging is slowing the program down. Records that
+	/// aren't enabled aren't counted. Returns zero before anything is logged
 	///
-	/// ```should_panic
-	/// # use log::Level;
-	/// # use log::LevelFilter;
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
 	/// # use minilog::Minilog;
-	/// //should panic
-	/// Minilog::init(LevelFilter::Info, "minilog_output_test.txt", "{level} - {msg}");
-	/// Minilog::log_or_panic(Level::Trace, "Trace!");
+	/// # use std::fs;
+	/// # use std::time::Duration;
+	/// Minilog::init(LevelFilter::Info, "minilog_log_cost_test.txt", "{level} - {msg}");
+	/// assert_eq!(Minilog::avg_log_cost(), Duration::ZERO);
+	/// for i in 0..10 {
+	///     info!("record {}", i);
+	/// }
+	/// # fs::remove_file("minilog_log_cost_test.txt").expect("Unable to delete test file.");
+	/// let cost = Minilog::avg_log_cost();
+	/// assert!(cost > Duration::ZERO);
+	/// assert!(cost < Duration::from_secs(1));
 	/// ```
+	pub fn avg_log_cost() -> Duration {
+		let records = LOG_COST_RECORDS.load(Ordering::Relaxed);
+		if records == 0 {
+			return Duration::ZERO;
+		}
+		Duration::from_nanos(LOG_COST_NANOS.load(Ordering::Relaxed) / records)
+	}
+	///Logs a message as if it happened at `time`, for backfilling
+	/// historical events. Only the rendered timestamp uses `time`; level
+	/// schedules, summaries and other time based behavior still go by the
+	/// logger clock
 	///
-	pub fn log_or_panic(loglevel: Level, msg: &str) {
-		if loglevel > max_level() {
-			panic!("{} is too low to log", loglevel);
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::time::{Duration, UNIX_EPOCH};
+	/// Minilog::init(LevelFilter::Info, "minilog_log_at_time_test.txt", "{timestamp} {msg}");
+	/// let event_time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+	/// Minilog::log_at_time(Level::Info, "imported event", event_time);
+	/// let file_contents =
+	///     fs::read_to_string("minilog_log_at_time_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_log_at_time_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "2001-09-09T01:46:40.000Z imported event\n");
+	/// ```
+	#[track_caller]
+	pub fn log_at_time(level: Level, msg: &str, time: SystemTime) {
+		TIMESTAMP_OVERRIDE.with(|timestamp| timestamp.set(Some(time)));
+		log_from_caller(level, format_args!("{}", msg));
+		TIMESTAMP_OVERRIDE.with(|timestamp| timestamp.set(None));
+	}
+	///Labels what the current thread is working on, rendered by the
+	/// `{operation}` token. Setting a new operation replaces the previous
+	/// one; see `clear_operation` to remove it
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// let fmt = "{?operation}[{operation}] {/operation}{msg}";
+	/// Minilog::init(LevelFilter::Info, "minilog_operation_test.txt", fmt);
+	/// Minilog::set_operation("resize-images");
+	/// info!("started");
+	/// Minilog::set_operation("send-mail");
+	/// info!("started");
+	/// Minilog::clear_operation();
+	/// info!("idle");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_operation_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_operation_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "[resize-images] started\n[send-mail] started\nidle\n");
+	/// ```
+	pub fn set_operation(operation: &str) {
+		OPERATION.with(|current| {
+			let mut current = current.borrow_mut();
+			current.clear();
+			current.push_str(operation);
+		});
+	}
+	///Removes the current thread's operation label, leaving the
+	/// `{operation}` token empty
+	pub fn clear_operation() {
+		OPERATION.with(|current| current.borrow_mut().clear());
+	}
+	///Labels the logical worker running on the current thread, rendered
+	/// by the `{worker}` token. Thread pools reuse threads for different
+	/// workers, so a pool can set this when handing a thread to a worker
+	/// instead of relying on thread names. An empty label clears it
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{info, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_worker_test.txt", "[{worker}] {msg}");
+	/// Minilog::set_worker_label("indexer-1");
+	/// info!("job done");
+	/// Minilog::set_worker_label("mailer-2");
+	/// info!("job done");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_worker_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_worker_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "[indexer-1] job done\n[mailer-2] job done\n");
+	/// ```
+	pub fn set_worker_label(label: &str) {
+		WORKER.with(|current| {
+			let mut current = current.borrow_mut();
+			current.clear();
+			current.push_str(label);
+		});
+	}
+	///Flushes stdout or stderr after every record. Stdout is otherwise only
+	/// flushed at the end of a line, so consumers reading it through a pipe
+	/// can be left waiting on framed records or a custom formatter's output
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use minilog::Minilog;
+	/// Minilog::set_flush_std(true);
+	/// ```
+	pub fn set_flush_std(flush: bool) {
+		FLUSH_STD.store(flush, Ordering::Relaxed);
+	}
+	///Logs a message in a category such as "security" or "billing", which is
+	/// independent of its level. The category is rendered by the
+	/// `{category}` token, can be filtered with `set_category_level`, and is
+	/// passed to channel sinks for routing. Enums can be used as categories
+	/// by passing their name
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::{Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// # use std::sync::mpsc;
+	/// let fmt = "{level}{?category} [{category}]{/category} - {msg}";
+	/// Minilog::init(LevelFilter::Info, "minilog_category_test.txt", fmt);
+	/// let (sender, receiver) = mpsc::channel();
+	/// Minilog::add_channel_sink(sender);
+	/// Minilog::log_cat(Level::Warn, "security", "login failed");
+	/// Minilog::log_cat(Level::Info, "performance", "cache warmed");
+	/// log::info!("uncategorized");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_category_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_category_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(
+	///     file_contents,
+	///     "WARN [security] - login failed\nINFO [performance] - cache warmed\nINFO - uncategorized\n"
+	/// );
+	/// let categories: Vec<Option<String>> =
+	///     receiver.try_iter().map(|record| record.category).collect();
+	/// assert_eq!(
+	///     categories,
+	///     [Some("security".to_owned()), Some("performance".to_owned()), None]
+	/// );
+	/// ```
+	#[track_caller]
+	pub fn log_cat(level: Level, category: &str, msg: &str) {
+		if let Some(&filter) = lock(&CATEGORY_LEVELS).get(category) {
+			if level > filter {
+				return;
+			}
 		}
-		let mut record = Record::builder();
-		log!(loglevel,
-			"{}", format!(
-				"{}", format_args!(
-					"{}", record
-						.args(format_args!("{}", msg))
-						.level(loglevel)
-						.build()
-						.args()
-				)
-			)
-		);
+		CATEGORY.with(|current| *current.borrow_mut() = Some(category.to_owned()));
+		log_from_caller(level, format_args!("{}", msg));
+		CATEGORY.with(|current| *current.borrow_mut() = None);
 	}
-	///logs a message, upgrading the log level if log level isn't high enough
+	///Only logs records of `category` at `level` or above. Other categories
+	/// and uncategorized records are unaffected
+	///
+	/// # Examples
+	///
 	/// ```
 	/// # use log::{Level, LevelFilter};
 	/// # use minilog::Minilog;
 	/// # use std::fs;
-	/// Minilog::init(LevelFilter::Info, "minilog_output_test.txt", "{level} - {msg}");
-	/// Minilog::log_upgrade(Level::Trace, "Trace!");
+	/// Minilog::init(LevelFilter::Info, "minilog_category_level_test.txt", "{category}: {msg}");
+	/// Minilog::set_category_level("performance", LevelFilter::Warn);
+	/// Minilog::log_cat(Level::Info, "performance", "cache warmed");
+	/// Minilog::log_cat(Level::Warn, "performance", "cache full");
+	/// Minilog::log_cat(Level::Info, "security", "key rotated");
 	/// let file_contents =
-	///		fs::read_to_string("minilog_output_test.txt").expect("Was unable to read file.");
-	///# fs::remove_file("minilog_output_test.txt").expect("Unable to delete test file.");
-	///assert_eq!(
-	///		file_contents,
-	///		"TRACE - Trace!\n"
-	///);
+	///     fs::read_to_string("minilog_category_level_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_category_level_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "performance: cache full\nsecurity: key rotated\n");
 	/// ```
-	pub fn log_upgrade(loglevel: Level, msg: &str) {
-		if loglevel > max_level() {
-			set_max_level(loglevel.to_level_filter())
+	pub fn set_category_level(category: &str, level: LevelFilter) {
+		lock(&CATEGORY_LEVELS).insert(category.to_owned(), level);
+	}
+	///Logs a message at a custom sub-level below Trace, available as the
+	/// `{sublevel}` token. Higher sub-levels are more important, and
+	/// `set_min_sublevel` filters them independently of the log level.
+	/// Sub-level records are still Trace records, so they are only logged
+	/// when Trace is enabled, and other loggers, formatters and `log` macros
+	/// only see Trace
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::LevelFilter;
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// let fmt = "{level}{?sublevel}.{sublevel}{/sublevel} {msg}";
+	/// Minilog::init(LevelFilter::Trace, "minilog_sublevel_test.txt", fmt);
+	/// Minilog::set_min_sublevel(2);
+	/// Minilog::log_sublevel(2, "verbose");
+	/// Minilog::log_sublevel(1, "very verbose");
+	/// log::trace!("plain trace");
+	/// let file_contents =
+	///     fs::read_to_string("minilog_sublevel_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_sublevel_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(file_contents, "TRACE.2 verbose\nTRACE plain trace\n");
+	/// ```
+	#[track_caller]
+	pub fn log_sublevel(sublevel: u8, msg: &str) {
+		if usize::from(sublevel) < MIN_SUBLEVEL.load(Ordering::Relaxed) {
+			return;
 		}
-		let mut record = Record::builder();
-		log!(loglevel,
-			"{}", format!(
-				"{}", format_args!(
-					"{}", record
-						.args(format_args!("{}", msg))
-						.level(loglevel)
-						.build()
-						.args()
-				)
-			)
-		);
+		SUBLEVEL.with(|current| current.set(Some(sublevel)));
+		log_from_caller(Level::Trace, format_args!("{}", msg));
+		SUBLEVEL.with(|current| current.set(None));
 	}
-	///logs a message, temporarily upgrading loglevel if it isn't high enough
+	///Drops records logged with `log_sublevel` below `sublevel`. Plain
+	/// Trace records aren't affected
+	pub fn set_min_sublevel(sublevel: u8) {
+		MIN_SUBLEVEL.store(usize::from(sublevel), Ordering::Relaxed);
+	}
+	///Logs a message followed by a backtrace of the current thread, if
+	/// `level` is enabled. Like `std::backtrace::Backtrace::capture`, the
+	/// backtrace is only captured when `RUST_LIB_BACKTRACE` or
+	/// `RUST_BACKTRACE` is set, and otherwise reads "disabled backtrace"
+	///
+	/// # Examples
+	///
 	/// ```
-    /// # use log::{Level, LevelFilter, trace};
+	/// # use log::{Level, LevelFilter};
 	/// # use minilog::Minilog;
 	/// # use std::fs;
-	/// Minilog::init(LevelFilter::Info, "minilog_output_test.txt", "{level} - {msg}");
-	/// Minilog::log_upgrade(Level::Trace, "Trace!");
+	/// std::env::set_var("RUST_LIB_BACKTRACE", "1");
+	/// Minilog::init(LevelFilter::Info, "minilog_backtrace_test.txt", "{level} - {msg}");
+	/// Minilog::log_backtrace(Level::Warn, "unexpected state");
+	/// Minilog::log_backtrace(Level::Debug, "not logged");
 	/// let file_contents =
-	///		fs::read_to_string("minilog_output_test.txt").expect("Was unable to read file.");
-	///# fs::remove_file("minilog_output_test.txt").expect("Unable to delete test file.");
-	///assert_eq!(
-	///		file_contents,
-	///		"TRACE - Trace!\n"
-	///);
+	///     fs::read_to_string("minilog_backtrace_test.txt").expect("Was unable to read file.");
+	/// # fs::remove_file("minilog_backtrace_test.txt").expect("Unable to delete test file.");
+	/// assert!(file_contents.starts_with("WARN - unexpected state\n"));
+	/// assert!(file_contents.contains("log_backtrace"));
+	/// assert!(!file_contents.contains("not logged"));
 	/// ```
-	pub fn log_upgrade_temp(loglevel: Level, msg: &str) {
-		if loglevel > max_level() {
-			let current_level = max_level(); 
-			Minilog::log_upgrade(loglevel, msg);
-			set_max_level(current_level);
+	#[track_caller]
+	pub fn log_backtrace(level: Level, msg: &str) {
+		if level > max_level() {
+			return;
 		}
+		log_from_caller(level, format_args!("{}\n{}", msg, Backtrace::capture()));
+	}
+	///Logs `n` synthetic records at the most verbose enabled level to the
+	/// configured output and reports the throughput achieved. Records
+	/// dropped on the way, e.g. by sampling, aren't counted
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use log::LevelFilter;
+	/// # use minilog::Minilog;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_benchmark_test.txt", "{level} - {msg}");
+	/// let report = Minilog::benchmark(100);
+	/// # fs::remove_file("minilog_benchmark_test.txt").expect("Unable to delete test file.");
+	/// assert_eq!(report.records, 100);
+	/// assert!(report.records_per_sec > 0.0);
+	/// assert!(report.bytes_per_sec > 0.0);
+	/// ```
+	///
+	/// ```
+	/// # use log::{Level, LevelFilter};
+	/// # use minilog::Minilog;
+	/// # use std::collections::BTreeMap;
+	/// # use std::fs;
+	/// Minilog::init(LevelFilter::Info, "minilog_benchmark_sampled_test.txt", "{level} - {msg}");
+	/// Minilog::set_severity_sampling(BTreeMap::from([(Level::Info, 0.0)]));
+	/// let report = Minilog::benchmark(100);
+	/// # let _ = fs::remove_file("minilog_benchmark_sampled_test.txt");
+	/// assert_eq!((report.records, report.bytes), (0, 0));
+	/// ```
+	pub fn benchmark(n: u64) -> ThroughputReport {
+		let level = max_level().to_level();
+		let bytes_before = BYTES_WRITTEN.load(Ordering::Relaxed);
+		let written = || level.map_or(0, Minilog::level_count);
+		let records_before = written();
+		let start = Instant::now();
+		if let Some(level) = level {
+			for i in 0..n {
+				log!(level, "minilog benchmark record {}", i);
+			}
+			logger().flush();
+		}
+		let elapsed = start.elapsed();
+		let records = written() - records_before;
+		let bytes = BYTES_WRITTEN.load(Ordering::Relaxed) - bytes_before;
+		let secs = elapsed.as_secs_f64();
+		let per_sec = |count: u64| if secs > 0.0 { count as f64 / secs } else { 0.0 };
+		ThroughputReport {
+			records,
+			bytes,
+			elapsed,
+			records_per_sec: per_sec(records),
+			bytes_per_sec: per_sec(bytes),
+		}
+	}
+}
+
+impl Minilog {
+	/// Sets this as the global logger
+	fn install(self, loglevel: LevelFilter, target: Target) -> Result<(), InitError> {
+		// Claimed up front, so that of two racing inits the one that loses
+		// is told a Minilog was initialized rather than some other logger
+		if INSTALLED.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+			return Err(InitError::AlreadyInitialized);
+		}
+		match set_boxed_logger(Box::new(self)) {
+			Ok(()) => {
+				*lock(&INIT_TIME) = Some(now());
+				*lock(&TARGET) = Some(target);
+				// Keeps a level raised before initializing, like a startup level
+				Minilog::set_log_level(loglevel);
+				Ok(())
+			}
+			Err(_) => {
+				INSTALLED.store(false, Ordering::SeqCst);
+				Err(InitError::ForeignLoggerInstalled)
+			}
+		}
+	}
+
+	/// Renders a record with the format string, shortening the message so
+	/// the line fits the terminal if auto width is on
+	fn render(&self, record: &Record, timestamp: SystemTime) -> String {
+		let target_formats = TARGET_FORMATS.read().unwrap_or_else(|e| e.into_inner());
+		let template = target_formats.get(record.target()).unwrap_or(&self.template);
+		let render = |fields: &RecordFields| {
+			if COLLAPSE_WHITESPACE.load(Ordering::Relaxed) {
+				template.render_collapsed(fields)
+			} else {
+				template.render(fields)
+			}
+		};
+		let mut fields = RecordFields::new(record, timestamp);
+		let line = render(&fields);
+		if !AUTO_WIDTH.load(Ordering::Relaxed) {
+			return line;
+		}
+		let overflow = line.chars().count().saturating_sub(terminal_width());
+		if overflow == 0 {
+			return line;
+		}
+		let keep = fields.message.chars().count().saturating_sub(overflow);
+		fields.message = fields.message.chars().take(keep).collect();
+		render(&fields)
 	}
-    ///returns option with the maximum log level, none if logging is off
-    ///```
-    ///# use log::{Level, LevelFilter, trace};
-    ///# use minilog::Minilog;
-    ///Minilog::init(LevelFilter::Trace, "minilog_output_test.txt", "{level} - {msg}");
-    ///assert_eq!(Minilog::log_level(), Some(Level::Trace));
-    ///```
-    pub fn log_level() -> Option<Level> {
-        max_level().to_level()
-    }
 }
 
 impl Log for Minilog {
-	///Returns whether logging is enabled for a given level
+	///Returns whether logging is enabled for a given level, taking any
+	/// schedule into account
 	fn enabled(&self, metadata: &Metadata) -> bool {
-		metadata.level() <= max_level()
+		let now = now();
+		expire_level_override(now);
+		if metadata.level() > max_level() {
+			return false;
+		}
+		if lock(&WARMUP_UNTIL).is_some_and(|until| now < until) {
+			return false;
+		}
+		match lock(&SCHEDULE).as_ref().and_then(|schedule| schedule.level_at(now)) {
+			Some(level) => metadata.level() <= level,
+			None => true,
+		}
 	}
 
 	///Logs a message to file, using the format string provided.
-	/// The "level", "msg", "modpath", "line", or "file" enclosed in
-	/// curly braces will be replaced.
+	/// Tokens enclosed in curly braces, listed by
+	/// [`Minilog::available_tokens`], will be replaced. Text between
+	/// `{?file}` and `{/file}` is only written if the record has a file,
+	/// and likewise for the other fields. A `:json` modifier, as in
+	/// `{msg:json}`, JSON-escapes the value.
 	/// # Panics
-	/// Panics if it can't open the file or write to it
+	/// Panics if it can't open the file or write to it. Failed writes to
+	/// stdout or stderr are ignored
 	fn log(&self, record: &Record) {
-		if self.enabled(record.metadata()) {
-			let log_msg = self.fmt_string
-				.replacen(
-					"{level}",
-					&format!("{}", format_args!("{}", record.level())),
-					1,
-				)
-				.replacen(
-					"{msg}",
-					&format!("{}", format_args!("{}", record.args())),
-					1,
-				)
-				.replacen(
-					"{modpath}",
-					&format!("{}", format_args!("{}", record.module_path().unwrap_or(""))),
-					1,
-				)
-				.replacen(
-					"{file}",
-					&format!("{}", format_args!("{}", record.file().unwrap_or(""))),
-					1,
-				)
-				.replacen(
-					"{line}",
-					&format!("{}", format_args!("{}", record.line().unwrap_or(0))),
-					1
-				);
-			if self.logfile_name == "stdout" {
-				println!("{}", log_msg);
-			} else if self.logfile_name == "stderr" {
-				eprintln!("{}", log_msg);
-			} else {
-				let mut file = OpenOptions::new()
-					.read(true)
-					.append(true)
-					.create(true)
-					.open(&self.logfile_name);
-				match &mut file {
-					Ok(file) => match writeln!(file, "{}", log_msg) {
-						Ok(_) => {}
-						Err(e) => panic!("{}: Write failed", e),
-					},
-					Err(e) => panic!("{}: Failed to write to logfile {}", e, &self.logfile_name),
+		if let Some(file) = record.file() {
+			if lock(&MUTED_FILES).iter().any(|muted| path_matches(file, muted)) {
+				return;
+			}
+		}
+		if let Some(level) = escalated_level(record) {
+			return self.log(
+				&Record::builder()
+					.args(*record.args())
+					.level(level)
+					.target(record.target())
+					.module_path(record.module_path())
+					.file(record.file())
+					.line(record.line())
+					.build(),
+			);
+		}
+		if self.enabled(record.metadata()) && sampled(record.level()) && deduped(record) {
+			let started = Instant::now();
+			let uses_offset = self.template.uses("offset")
+				|| TARGET_FORMATS
+					.read()
+					.unwrap_or_else(|e| e.into_inner())
+					.values()
+					.any(|template| template.uses("offset"));
+			let order = uses_offset.then(|| lock(&RECORD_ORDER));
+			let timestamp = TIMESTAMP_OVERRIDE.with(Cell::get).unwrap_or_else(now);
+			let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+			let formatted = panic::catch_unwind(AssertUnwindSafe(|| {
+				let line = match &*FORMATTER.read().unwrap_or_else(|e| e.into_inner()) {
+					Some(formatter) => formatter.format(
+						record,
+						&RenderMeta {
+							timestamp,
+							sequence,
+							pid: std::process::id(),
+						},
+					),
+					None => self.render(record, timestamp),
+				};
+				(line, protobuf_entry(record, timestamp, None))
+			}));
+			let (mut log_msg, protobuf) = match formatted {
+				Ok(formatted) => formatted,
+				Err(_) => {
+					let line = match &*ON_FORMAT_ERROR.read().unwrap_or_else(|e| e.into_inner()) {
+						OnFormatError::Skip => return,
+						OnFormatError::Raw => record.args().to_string(),
+						OnFormatError::Placeholder(placeholder) => placeholder.clone(),
+					};
+					let protobuf = protobuf_entry(record, timestamp, Some(&line));
+					(line, protobuf)
+				}
+			};
+			// Leave room for the signature, if lines are signed
+			let max_line_bytes =
+				MAX_LINE_BYTES.load(Ordering::Relaxed).saturating_sub(signature_len());
+			if log_msg.len() > max_line_bytes {
+				truncate_line(&mut log_msg, max_line_bytes, &lock(&TRUNCATION_INDICATOR));
+			}
+			#[cfg(feature = "signing")]
+			if let Some(key) = &*SIGNING_KEY.read().unwrap_or_else(|e| e.into_inner()) {
+				key.sign(&mut log_msg);
+			}
+			let bytes = record_bytes(record, &log_msg, protobuf);
+			if write_record(record, timestamp, &bytes) {
+				BYTES_WRITTEN.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+				LEVEL_COUNTS[record.level() as usize - 1].fetch_add(1, Ordering::Relaxed);
+			}
+			drop(order);
+			if let Some(liveness) = lock(&LIVENESS).as_mut() {
+				// Backfilled records mustn't move the liveness time
+				let now = now();
+				if now >= liveness.touched + liveness.interval {
+					liveness.touch(now);
 				}
 			}
+			LOG_COST_NANOS.fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+			LOG_COST_RECORDS.fetch_add(1, Ordering::Relaxed);
+			if let Some(summary) = lock(&DAILY_SUMMARY).as_mut() {
+				summary.record(record);
+			}
+			if record.level() == Level::Error {
+				if let Some((duration, level)) = *lock(&ERROR_BURST) {
+					// Bursts go by the logger clock, even for backfilled records
+					raise_level_until(level, now() + duration);
+				}
+			}
+			#[cfg(feature = "websocket")]
+			for server in lock(&WEBSOCKET_SERVERS).iter() {
+				let _ = server.lines.send(log_msg.clone());
+			}
+			let mut channels = lock(&CHANNEL_SINKS);
+			if !channels.is_empty() {
+				let owned = LogRecordOwned::new(record, timestamp);
+				channels.retain(|sender| sender.send(owned.clone()).is_ok());
+			}
 		}
 	}
 
-	///preserved for trait implementation
-	fn flush(&self) {}
+	/// Replaces the log file with the records buffered since the last flush
+	/// if atomic rewrites are on
+	/// # Panics
+	/// Panics if it can't write the temporary file or rename it
+	fn flush(&self) {
+		let target = lock(&TARGET);
+		let path = match target.as_ref() {
+			Some(Target::File(path)) => path,
+			_ => return,
+		};
+		let contents = match lock(&ATOMIC_BUFFER).as_mut() {
+			Some(buffer) => std::mem::take(buffer),
+			None => return,
+		};
+		replace_log_file(path, contents);
+	}
+}
+
+/// Replaces the log file at `path` with `contents` through a temporary
+/// file, so readers never see it half written
+/// # Panics
+/// Panics if it can't write the temporary file or rename it
+fn replace_log_file(path: &str, contents: Vec<u8>) {
+	let temp_path = format!("{}.tmp", path);
+	if let Err(e) = fs::write(&temp_path, contents) {
+		panic!("{}: Failed to write to temporary file {}", e, temp_path);
+	}
+	if let Err(e) = fs::rename(&temp_path, path) {
+		panic!("{}: Failed to replace logfile {}", e, path);
+	}
 }
 
 #[cfg(test)]
@@ -239,6 +4827,69 @@ mod tests {
 	use super::*;
 	use std::fs;
 	use std::path::Path;
+	use std::process::{Command, Stdio};
+	#[test]
+	fn test_path_matches() {
+		assert!(path_matches("foo.rs", "foo.rs"));
+		assert!(path_matches("src/foo.rs", "foo.rs"));
+		assert!(path_matches("src\\foo.rs", "foo.rs"));
+		assert!(path_matches("src/foo.rs", "src/foo.rs"));
+		assert!(!path_matches("src/barfoo.rs", "foo.rs"));
+		assert!(!path_matches("src/foo.rs", "bar.rs"));
+	}
+	#[test]
+	fn test_path_component() {
+		assert_eq!(path_component("tenant-a"), "tenant-a");
+		assert_eq!(path_component("../escaped"), "___escaped");
+		assert_eq!(path_component("a/b\\c\0d"), "a_b_c_d");
+		assert_eq!(path_component("v1.2"), "v1.2");
+	}
+	#[test]
+	fn test_truncate_line() {
+		let mut line = "héllo world".to_owned();
+		truncate_line(&mut line, 2, "");
+		assert_eq!(line, "h");
+		let mut line = "hello world".to_owned();
+		truncate_line(&mut line, 8, "...");
+		assert_eq!(line, "hello...");
+		let mut line = "hello world".to_owned();
+		truncate_line(&mut line, 2, "...");
+		assert_eq!(line, "he");
+		let mut line = "short".to_owned();
+		truncate_line(&mut line, 8, "...");
+		assert_eq!(line, "short");
+	}
+	#[test]
+	fn test_read_truncated_frame() {
+		let bytes = [0, 0, 0, 2, b'o', b'k', 0, 0, 0, 9, b'c', b'u', b't'];
+		let mut frames = Minilog::read_frames(&bytes[..]);
+		assert_eq!(frames.next().unwrap().unwrap(), "ok");
+		assert_eq!(
+			frames.next().unwrap().unwrap_err().kind(),
+			io::ErrorKind::UnexpectedEof
+		);
+		assert!(frames.next().is_none());
+	}
+	#[test]
+	fn test_available_tokens() {
+		let tokens = Minilog::available_tokens();
+		for core in ["level", "msg", "modpath", "file", "line", "timestamp"] {
+			assert!(tokens.iter().any(|&(token, description)| token == core && !description.is_empty()));
+		}
+		let args = format_args!("message");
+		let record = Record::builder().args(args).build();
+		let fields = RecordFields::new(&record, SystemTime::UNIX_EPOCH);
+		for (token, _) in tokens {
+			assert!(fields.value(token).is_some(), "{} isn't rendered", token);
+		}
+	}
+	#[test]
+	fn test_kv_value() {
+		assert_eq!(kv_value("GET"), "GET");
+		assert_eq!(kv_value(""), "\"\"");
+		assert_eq!(kv_value("a b"), "\"a b\"");
+		assert_eq!(kv_value("say \"hi\""), "\"say \\\"hi\\\"\"");
+	}
 	#[test]
 	fn test() {
 		match Minilog::init(LevelFilter::Info, "Minilog_test_main.txt", "{level}: {msg}") {
@@ -267,6 +4918,91 @@ mod tests {
 			Err(e) => panic!("{}: Could not set the logger!", e),
 		}
 		info!("Log message");
-		assert_eq!(false, Path::new("stdout").exists())
+		assert!(!Path::new("stdout").exists())
+	}
+	#[test]
+	#[cfg(unix)]
+	fn test_closed_pipe_doesnt_panic() {
+		let mut child = Command::new(std::env::current_exe().expect("No test executable"))
+			.args(["tests::log_to_closed_stderr", "--exact", "--ignored", "--nocapture"])
+			.stdout(Stdio::null())
+			.stderr(Stdio::piped())
+			.spawn()
+			.expect("Unable to start child test");
+		drop(child.stderr.take());
+		assert!(child.wait().expect("Child test didn't run").success());
+	}
+	#[test]
+	#[cfg(unix)]
+	fn test_flush_std_reaches_pipe() {
+		let mut child = Command::new(std::env::current_exe().expect("No test executable"))
+			.args(["tests::log_flushed_to_stdout", "--exact", "--ignored", "--nocapture"])
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::null())
+			.spawn()
+			.expect("Unable to start child test");
+		let mut stdout = child.stdout.take().unwrap();
+		let (sender, receiver) = std::sync::mpsc::channel();
+		std::thread::spawn(move || {
+			let mut output = Vec::new();
+			let mut buf = [0; 256];
+			while let Ok(read @ 1..) = stdout.read(&mut buf) {
+				output.extend_from_slice(&buf[..read]);
+				if output.windows(9).any(|window| window == b"\0\0\0\x05xyzzy") {
+					let _ = sender.send(());
+				}
+			}
+		});
+		let received = receiver.recv_timeout(Duration::from_secs(10));
+		// Closing stdin lets the child finish
+		drop(child.stdin.take());
+		child.wait().expect("Child test didn't run");
+		assert!(received.is_ok(), "Record wasn't flushed to the pipe");
+	}
+	#[test]
+	fn test_fallback_to_stderr() {
+		let output = Command::new(std::env::current_exe().expect("No test executable"))
+			.args(["tests::log_to_unopenable_file", "--exact", "--ignored", "--nocapture"])
+			.stdout(Stdio::null())
+			.output()
+			.expect("Unable to run child test");
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		assert!(output.status.success(), "{}", stderr);
+		assert!(stderr.contains("WARN - Failed to open logfile /nonexistent/minilog_fallback_test.txt"));
+		assert!(stderr.contains("INFO - still logged\n"));
+	}
+	#[test]
+	#[ignore]
+	// Run by test_fallback_to_stderr, which reads its stderr
+	fn log_to_unopenable_file() {
+		Minilog::set_fallback_to_stderr_on_init_failure(true);
+		let path = "/nonexistent/minilog_fallback_test.txt";
+		Minilog::init(LevelFilter::Info, path, "{level} - {msg}").expect("Could not set the logger!");
+		info!("still logged");
+	}
+	#[test]
+	#[ignore]
+	// Run by test_flush_std_reaches_pipe, which holds its stdin open until
+	// the record shows up on stdout
+	fn log_flushed_to_stdout() {
+		Minilog::init(LevelFilter::Info, "stdout", "{msg}").expect("Could not set the logger!");
+		// Frames don't end in a newline, so only an explicit flush sends them
+		Minilog::set_framed(true);
+		Minilog::set_flush_std(true);
+		info!("xyzzy");
+		let _ = std::io::stdin().read(&mut [0]);
+	}
+	#[test]
+	#[ignore]
+	// Run by test_closed_pipe_doesnt_panic with the read end of its stderr closed
+	fn log_to_closed_stderr() {
+		match Minilog::init(LevelFilter::Info, "stderr", "{level}: {msg}") {
+			Ok(_) => {}
+			Err(e) => panic!("{}: Could not set the logger!", e),
+		}
+		for i in 0..1000 {
+			error!("Log message {}", i);
+		}
 	}
 }
@@ -0,0 +1,196 @@
+//! Protocol Buffers encoding of records, without depending on a protobuf
+//! library. Records are encoded as this message:
+//!
+//! ```proto
+//! syntax = "proto3";
+//!
+//! package minilog;
+//!
+//! message LogRecord {
+//!   // 1 for ERROR up to 5 for TRACE
+//!   uint32 level = 1;
+//!   // Milliseconds since the Unix epoch
+//!   int64 timestamp_millis = 2;
+//!   string message = 3;
+//!   map<string, string> fields = 4;
+//! }
+//! ```
+//!
+//! and written length-delimited, each preceded by its length as a varint,
+//! the way `writeDelimitedTo` and `parseDelimitedFrom` expect.
+
+use std::io;
+
+/// A record as carried by a `LogRecord` message
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Message {
+	pub(crate) level: u32,
+	pub(crate) timestamp_millis: i64,
+	pub(crate) message: String,
+	pub(crate) fields: Vec<(String, String)>,
+}
+
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const LEN: u8 = 2;
+const FIXED32: u8 = 5;
+
+impl Message {
+	/// Encodes the message preceded by its length. Fields holding their
+	/// default value are left out, as proto3 does
+	pub(crate) fn encode_delimited(&self) -> Vec<u8> {
+		let mut body = Vec::new();
+		if self.level != 0 {
+			push_tag(&mut body, 1, VARINT);
+			push_varint(&mut body, u64::from(self.level));
+		}
+		if self.timestamp_millis != 0 {
+			push_tag(&mut body, 2, VARINT);
+			push_varint(&mut body, self.timestamp_millis as u64);
+		}
+		if !self.message.is_empty() {
+			push_bytes(&mut body, 3, self.message.as_bytes());
+		}
+		for (key, value) in &self.fields {
+			let mut entry = Vec::new();
+			push_bytes(&mut entry, 1, key.as_bytes());
+			push_bytes(&mut entry, 2, value.as_bytes());
+			push_bytes(&mut body, 4, &entry);
+		}
+		let mut delimited = Vec::new();
+		push_varint(&mut delimited, body.len() as u64);
+		delimited.extend(body);
+		delimited
+	}
+
+	/// Decodes a message, without its length. Unknown fields are skipped
+	pub(crate) fn decode(mut body: &[u8]) -> io::Result<Message> {
+		let mut message = Message::default();
+		while !body.is_empty() {
+			let (number, value) = read_field(&mut body)?;
+			match (number, value) {
+				(1, Value::Varint(level)) => message.level = level as u32,
+				(2, Value::Varint(millis)) => message.timestamp_millis = millis as i64,
+				(3, Value::Len(text)) => message.message = utf8(text)?,
+				(4, Value::Len(mut entry)) => {
+					let (mut key, mut value) = (String::new(), String::new());
+					while !entry.is_empty() {
+						match read_field(&mut entry)? {
+							(1, Value::Len(text)) => key = utf8(text)?,
+							(2, Value::Len(text)) => value = utf8(text)?,
+							_ => {}
+						}
+					}
+					message.fields.push((key, value));
+				}
+				_ => {}
+			}
+		}
+		Ok(message)
+	}
+}
+
+enum Value<'a> {
+	Varint(u64),
+	Len(&'a [u8]),
+	Fixed,
+}
+
+fn invalid(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn utf8(bytes: &[u8]) -> io::Result<String> {
+	String::from_utf8(bytes.to_vec()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn push_tag(out: &mut Vec<u8>, number: u32, wire_type: u8) {
+	push_varint(out, u64::from(number) << 3 | u64::from(wire_type));
+}
+
+fn push_bytes(out: &mut Vec<u8>, number: u32, bytes: &[u8]) {
+	push_tag(out, number, LEN);
+	push_varint(out, bytes.len() as u64);
+	out.extend_from_slice(bytes);
+}
+
+pub(crate) fn push_varint(out: &mut Vec<u8>, mut value: u64) {
+	while value >= 0x80 {
+		out.push(value as u8 | 0x80);
+		value >>= 7;
+	}
+	out.push(value as u8);
+}
+
+/// Reads a varint off the front of `bytes`
+pub(crate) fn read_varint(bytes: &mut &[u8]) -> io::Result<u64> {
+	let mut value = 0;
+	for shift in (0..64).step_by(7) {
+		let (&byte, rest) = bytes.split_first().ok_or_else(|| invalid("Varint is cut short"))?;
+		*bytes = rest;
+		value |= u64::from(byte & 0x7f) << shift;
+		if byte < 0x80 {
+			return Ok(value);
+		}
+	}
+	Err(invalid("Varint is too long"))
+}
+
+/// Reads a field off the front of `bytes`, returning its number and value
+fn read_field<'a>(bytes: &mut &'a [u8]) -> io::Result<(u64, Value<'a>)> {
+	let tag = read_varint(bytes)?;
+	let value = match (tag & 7) as u8 {
+		VARINT => Value::Varint(read_varint(bytes)?),
+		LEN => {
+			let len = read_varint(bytes)?;
+			if len > bytes.len() as u64 {
+				return Err(invalid("Field is cut short"));
+			}
+			let (value, rest) = bytes.split_at(len as usize);
+			*bytes = rest;
+			Value::Len(value)
+		}
+		wire_type @ (FIXED64 | FIXED32) => {
+			let len = if wire_type == FIXED64 { 8 } else { 4 };
+			if len > bytes.len() {
+				return Err(invalid("Field is cut short"));
+			}
+			*bytes = &bytes[len..];
+			Value::Fixed
+		}
+		_ => return Err(invalid("Unsupported wire type")),
+	};
+	Ok((tag >> 3, value))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn test_round_trip() {
+		let message = Message {
+			level: 2,
+			timestamp_millis: -1500,
+			message: "disk full".to_owned(),
+			fields: vec![("tenant".to_owned(), "7".to_owned())],
+		};
+		let delimited = message.encode_delimited();
+		let mut rest = &delimited[..];
+		let len = read_varint(&mut rest).unwrap();
+		assert_eq!(len as usize, rest.len());
+		assert_eq!(Message::decode(rest).unwrap(), message);
+	}
+	#[test]
+	fn test_encoding() {
+		let message = Message {
+			level: 3,
+			message: "hi".to_owned(),
+			..Message::default()
+		};
+		assert_eq!(message.encode_delimited(), [6, 0x08, 3, 0x1a, 2, b'h', b'i']);
+		// Fields 5 (varint) and 6 (fixed32) are unknown
+		let decoded = Message::decode(&[0x28, 0x96, 0x01, 0x35, 1, 2, 3, 4, 0x08, 3]).unwrap();
+		assert_eq!(decoded.level, 3);
+		assert!(Message::decode(&[0x1a, 5, b'h']).is_err());
+	}
+}
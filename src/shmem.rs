@@ -0,0 +1,251 @@
+//! Ring buffer of records in a named POSIX shared memory segment, so that
+//! a collector in another process can read them without going through a
+//! file or socket
+//!
+//! The segment starts with a header of three `u64`s: the write position
+//! reserved by the writer, the write position of the last complete record,
+//! and the capacity of the data area that follows. Positions count bytes
+//! ever written and wrap around the data area. Each record is its length
+//! as a little-endian `u32` followed by its bytes.
+
+use std::ffi::CString;
+use std::io;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicU64, Ordering};
+
+const HEADER_LEN: usize = 24;
+
+/// A mapped shared memory segment holding a ring of records
+pub(crate) struct Ring {
+	data: *mut u8,
+	len: usize,
+}
+
+// SAFETY: the mapping lives as long as the ring, and the header is only
+// accessed atomically. Writes are serialized by the logger's target lock
+unsafe impl Send for Ring {}
+unsafe impl Sync for Ring {}
+
+impl Ring {
+	/// Creates the segment `name`, or empties it if it exists, with room
+	/// for `size` bytes including the header
+	pub(crate) fn create(name: &str, size: usize) -> io::Result<Ring> {
+		if size <= HEADER_LEN {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"Shared memory segment is too small",
+			));
+		}
+		let ring = Ring::map(name, libc::O_CREAT | libc::O_RDWR, Some(size))?;
+		ring.header(0).store(0, Ordering::Relaxed);
+		ring.header(1).store(0, Ordering::Relaxed);
+		ring.header(2).store((size - HEADER_LEN) as u64, Ordering::Release);
+		Ok(ring)
+	}
+
+	/// Maps the existing segment `name`
+	pub(crate) fn open(name: &str) -> io::Result<Ring> {
+		let ring = Ring::map(name, libc::O_RDWR, None)?;
+		if ring.capacity() == 0 {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"Not a Minilog shared memory segment",
+			));
+		}
+		Ok(ring)
+	}
+
+	/// Removes the segment `name`. Existing mappings stay usable
+	pub(crate) fn unlink(name: &str) -> io::Result<()> {
+		let name = segment_name(name)?;
+		// SAFETY: `name` is a valid C string
+		if unsafe { libc::shm_unlink(name.as_ptr()) } == -1 {
+			return Err(io::Error::last_os_error());
+		}
+		Ok(())
+	}
+
+	fn map(name: &str, flags: libc::c_int, size: Option<usize>) -> io::Result<Ring> {
+		let name = segment_name(name)?;
+		// SAFETY: `name` is a valid C string, and the descriptor is closed
+		// before returning, since the mapping keeps the segment alive
+		unsafe {
+			let fd = libc::shm_open(name.as_ptr(), flags, 0o600 as libc::c_uint);
+			if fd == -1 {
+				return Err(io::Error::last_os_error());
+			}
+			let len = match size {
+				Some(size) => {
+					if libc::ftruncate(fd, 0) == -1 || libc::ftruncate(fd, size as libc::off_t) == -1
+					{
+						let e = io::Error::last_os_error();
+						libc::close(fd);
+						return Err(e);
+					}
+					size
+				}
+				None => {
+					let mut stat: libc::stat = std::mem::zeroed();
+					if libc::fstat(fd, &mut stat) == -1 {
+						let e = io::Error::last_os_error();
+						libc::close(fd);
+						return Err(e);
+					}
+					stat.st_size as usize
+				}
+			};
+			if len <= HEADER_LEN {
+				libc::close(fd);
+				return Err(io::Error::new(
+					io::ErrorKind::InvalidData,
+					"Not a Minilog shared memory segment",
+				));
+			}
+			let data = libc::mmap(
+				ptr::null_mut(),
+				len,
+				libc::PROT_READ | libc::PROT_WRITE,
+				libc::MAP_SHARED,
+				fd,
+				0,
+			);
+			libc::close(fd);
+			if data == libc::MAP_FAILED {
+				return Err(io::Error::last_os_error());
+			}
+			Ok(Ring {
+				data: data as *mut u8,
+				len,
+			})
+		}
+	}
+
+	fn header(&self, index: usize) -> &AtomicU64 {
+		// SAFETY: the header is within the mapping, and mappings are page
+		// aligned
+		unsafe { &*(self.data as *const AtomicU64).add(index) }
+	}
+
+	fn capacity(&self) -> u64 {
+		self.header(2).load(Ordering::Acquire).min((self.len - HEADER_LEN) as u64)
+	}
+
+	/// Copies `bytes` into the data area starting at `position`, wrapping
+	/// around its end
+	fn copy_in(&self, position: u64, bytes: &[u8]) {
+		let capacity = self.capacity() as usize;
+		let start = (position % capacity as u64) as usize;
+		let first = bytes.len().min(capacity - start);
+		// SAFETY: both ranges are within the data area
+		unsafe {
+			let area = self.data.add(HEADER_LEN);
+			ptr::copy_nonoverlapping(bytes.as_ptr(), area.add(start), first);
+			ptr::copy_nonoverlapping(bytes[first..].as_ptr(), area, bytes.len() - first);
+		}
+	}
+
+	/// Copies bytes out of the data area starting at `position`, wrapping
+	/// around its end
+	fn copy_out(&self, position: u64, out: &mut [u8]) {
+		let capacity = self.capacity() as usize;
+		let start = (position % capacity as u64) as usize;
+		let first = out.len().min(capacity - start);
+		// SAFETY: both ranges are within the data area
+		unsafe {
+			let area = self.data.add(HEADER_LEN);
+			ptr::copy_nonoverlapping(area.add(start), out.as_mut_ptr(), first);
+			ptr::copy_nonoverlapping(area, out[first..].as_mut_ptr(), out.len() - first);
+		}
+	}
+
+	/// Appends a record, returning `false` if it can't fit in the ring
+	pub(crate) fn push(&self, bytes: &[u8]) -> bool {
+		let record_len = 4 + bytes.len() as u64;
+		if record_len > self.capacity() || bytes.len() > u32::MAX as usize {
+			return false;
+		}
+		let start = self.header(1).load(Ordering::Relaxed);
+		let end = start + record_len;
+		// Readers check the reserved position after copying a record, to
+		// tell whether it was being overwritten meanwhile
+		self.header(0).store(end, Ordering::Relaxed);
+		fence(Ordering::Release);
+		self.copy_in(start, &(bytes.len() as u32).to_le_bytes());
+		self.copy_in(start + 4, bytes);
+		self.header(1).store(end, Ordering::Release);
+		true
+	}
+
+	/// Position of the oldest record that is still intact
+	pub(crate) fn oldest(&self) -> u64 {
+		let written = self.header(1).load(Ordering::Acquire);
+		if written <= self.capacity() {
+			0
+		} else {
+			// Records don't line up with the wrap-around, so the oldest
+			// complete one can't be found; start with the next one written
+			written
+		}
+	}
+
+	/// Reads the record at `position`, returning it with the position of
+	/// the next one, or `None` if nothing has been written there yet. A
+	/// record overwritten before it could be read comes back as `None`,
+	/// with the position of the newest record to carry on from
+	pub(crate) fn read(&self, position: u64) -> Option<(Option<Vec<u8>>, u64)> {
+		let written = self.header(1).load(Ordering::Acquire);
+		let capacity = self.capacity();
+		if position >= written {
+			return None;
+		}
+		if written - position > capacity {
+			return Some((None, written));
+		}
+		let mut len = [0; 4];
+		self.copy_out(position, &mut len);
+		let next = position + 4 + u64::from(u32::from_le_bytes(len));
+		let mut bytes = vec![0; (next - position - 4).min(capacity) as usize];
+		self.copy_out(position + 4, &mut bytes);
+		fence(Ordering::Acquire);
+		if next > written || self.header(0).load(Ordering::Relaxed) - position > capacity {
+			return Some((None, written));
+		}
+		Some((Some(bytes), next))
+	}
+}
+
+impl Drop for Ring {
+	fn drop(&mut self) {
+		// SAFETY: the mapping was created by `map` with this length
+		unsafe {
+			libc::munmap(self.data as *mut libc::c_void, self.len);
+		}
+	}
+}
+
+/// POSIX shared memory names start with a single slash
+fn segment_name(name: &str) -> io::Result<CString> {
+	CString::new(format!("/{}", name.trim_start_matches('/')))
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn test_overwritten_records() {
+		let name = format!("minilog_ring_test_{}", std::process::id());
+		let writer = Ring::create(&name, HEADER_LEN + 16).expect("Unable to create segment");
+		let reader = Ring::open(&name).expect("Unable to open segment");
+		Ring::unlink(&name).expect("Unable to remove segment");
+		assert!(!writer.push(&[0; 13]));
+		assert!(writer.push(b"one\n"));
+		assert_eq!(reader.read(0), Some((Some(b"one\n".to_vec()), 8)));
+		assert!(writer.push(b"two\n"));
+		assert!(writer.push(b"three\n"));
+		// "two" was overwritten by "three" wrapping around
+		assert_eq!(reader.read(8), Some((None, 26)));
+		assert_eq!(reader.read(26), None);
+		assert_eq!(reader.oldest(), 26);
+	}
+}
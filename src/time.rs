@@ -0,0 +1,130 @@
+//! Calendar helpers for rendering `SystemTime`s without pulling in a
+//! date library. All dates are in UTC.
+
+use std::convert::TryFrom;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub(crate) const SECS_PER_DAY: i64 = 86_400;
+const MILLIS_PER_DAY: i64 = SECS_PER_DAY * 1000;
+/// Rendered in place of timestamps outside the years 0000 to 9999, which
+/// RFC 3339 can't represent
+const RFC3339_FALLBACK: &str = "1970-01-01T00:00:00.000Z";
+
+/// Milliseconds relative to the Unix epoch, negative for earlier times.
+/// Saturates for times too far from the epoch to fit
+pub(crate) fn unix_millis(time: SystemTime) -> i64 {
+	match time.duration_since(UNIX_EPOCH) {
+		Ok(since) => i64::try_from(since.as_millis()).unwrap_or(i64::MAX),
+		Err(e) => i64::try_from(e.duration().as_millis()).map_or(i64::MIN, |millis| -millis),
+	}
+}
+
+/// Whole seconds relative to the Unix epoch, rounded down
+pub(crate) fn unix_secs(time: SystemTime) -> i64 {
+	unix_millis(time).div_euclid(1000)
+}
+
+/// Days since the Unix epoch of the UTC day containing `time`
+pub(crate) fn unix_day(time: SystemTime) -> i64 {
+	unix_secs(time).div_euclid(SECS_PER_DAY)
+}
+
+/// Converts days since the Unix epoch into a (year, month, day) date
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+	let z = days + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z.rem_euclid(146_097);
+	let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+	let year = yoe + era * 400 + i64::from(month <= 2);
+	(year, month, day)
+}
+
+/// Formats a day since the Unix epoch as `YYYY-MM-DD`
+pub(crate) fn format_date(days: i64) -> String {
+	let (year, month, day) = civil_from_days(days);
+	format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Formats `time` as an RFC 3339 timestamp with millisecond precision,
+/// e.g. `2026-10-14T21:00:00.000Z`. Times from clocks that are wildly off,
+/// outside the years 0000 to 9999, are rendered as the Unix epoch
+pub(crate) fn format_rfc3339(time: SystemTime) -> String {
+	let millis = unix_millis(time);
+	let (year, month, day) = civil_from_days(millis.div_euclid(MILLIS_PER_DAY));
+	if !(0..=9999).contains(&year) {
+		return RFC3339_FALLBACK.to_owned();
+	}
+	let of_day = millis.rem_euclid(MILLIS_PER_DAY);
+	format!(
+		"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+		year,
+		month,
+		day,
+		of_day / 3_600_000,
+		of_day / 60_000 % 60,
+		of_day / 1000 % 60,
+		of_day % 1000
+	)
+}
+
+/// Formats `time` the way the Common Log Format expects, e.g.
+/// `10/Oct/2000:13:55:36 +0000`
+pub(crate) fn format_clf(time: SystemTime) -> String {
+	const MONTHS: [&str; 12] = [
+		"Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+	];
+	let secs = unix_secs(time);
+	let (year, month, day) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+	let of_day = secs.rem_euclid(SECS_PER_DAY);
+	format!(
+		"{:02}/{}/{:04}:{:02}:{:02}:{:02} +0000",
+		day,
+		MONTHS[month as usize - 1],
+		year,
+		of_day / 3600,
+		of_day / 60 % 60,
+		of_day % 60
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+	#[test]
+	fn test_civil_from_days() {
+		assert_eq!(civil_from_days(0), (1970, 1, 1));
+		assert_eq!(civil_from_days(-1), (1969, 12, 31));
+		assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+		assert_eq!(civil_from_days(20_740), (2026, 10, 14));
+	}
+	#[test]
+	fn test_format_rfc3339() {
+		let time = UNIX_EPOCH + Duration::from_millis(1_792_011_600_042);
+		assert_eq!(format_rfc3339(time), "2026-10-14T21:00:00.042Z");
+		let before_epoch = UNIX_EPOCH - Duration::from_millis(1500);
+		assert_eq!(format_rfc3339(before_epoch), "1969-12-31T23:59:58.500Z");
+		let year_zero = UNIX_EPOCH - Duration::from_secs(62_167_219_200);
+		assert_eq!(format_rfc3339(year_zero), "0000-01-01T00:00:00.000Z");
+		let before_year_zero = year_zero - Duration::from_millis(1);
+		assert_eq!(format_rfc3339(before_year_zero), RFC3339_FALLBACK);
+		let far_future = UNIX_EPOCH + Duration::from_secs(u64::MAX / 2);
+		assert_eq!(format_rfc3339(far_future), RFC3339_FALLBACK);
+	}
+	#[test]
+	fn test_format_clf() {
+		let time = UNIX_EPOCH + Duration::from_secs(971_186_136);
+		assert_eq!(format_clf(time), "10/Oct/2000:13:55:36 +0000");
+	}
+	#[test]
+	fn test_unix_day() {
+		let before_epoch = UNIX_EPOCH - Duration::from_secs(1);
+		assert_eq!(unix_day(before_epoch), -1);
+		assert_eq!(unix_day(UNIX_EPOCH + Duration::from_secs(86_399)), 0);
+		assert_eq!(format_date(unix_day(UNIX_EPOCH + Duration::from_secs(86_400))), "1970-01-02");
+	}
+}